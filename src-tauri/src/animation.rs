@@ -0,0 +1,272 @@
+//! High-quality animated GIF and WebP export from video clips.
+//!
+//! GIFs use a single filter graph that generates an optimised palette with
+//! `palettegen` and applies it with `paletteuse`, which avoids the banding and
+//! noise of a plain codec switch.
+
+use crate::error::FileDittoError;
+use crate::ffmpeg;
+use crate::job::{self, JobContext};
+use crate::metadata;
+use crate::output::AtomicOutput;
+use crate::path;
+use crate::settings::{CollisionPolicy, CollisionSettings};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use tauri::AppHandle;
+use tracing::info;
+
+/// Default frame rate for animated output.
+const DEFAULT_FPS: u32 = 12;
+
+/// Default width in pixels for animated output.
+const DEFAULT_WIDTH: u32 = 480;
+
+/// Animated output formats.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnimationFormat {
+    Gif,
+    Webp,
+}
+
+impl AnimationFormat {
+    /// FFmpeg filters and encoders the format is built with.
+    fn required_features(self) -> &'static [&'static str] {
+        match self {
+            AnimationFormat::Gif => &["palettegen", "paletteuse"],
+            AnimationFormat::Webp => &["libwebp"],
+        }
+    }
+}
+
+/// Dithering algorithms supported by FFmpeg's `paletteuse` filter.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DitherMode {
+    Bayer,
+    Heckbert,
+    FloydSteinberg,
+    #[default]
+    Sierra2_4a,
+    None,
+}
+
+impl DitherMode {
+    fn as_filter_value(self) -> &'static str {
+        match self {
+            DitherMode::Bayer => "bayer:bayer_scale=3",
+            DitherMode::Heckbert => "heckbert",
+            DitherMode::FloydSteinberg => "floyd_steinberg",
+            DitherMode::Sierra2_4a => "sierra2_4a",
+            DitherMode::None => "none",
+        }
+    }
+}
+
+/// Options for exporting a video clip as an animated image.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnimationOptions {
+    pub format: AnimationFormat,
+    pub fps: Option<u32>,
+    /// Output width in pixels; the height follows the source aspect ratio.
+    pub width: Option<u32>,
+    /// Number of times to loop; 0 loops forever.
+    pub loop_count: Option<u32>,
+    /// GIF only.
+    pub dither: Option<DitherMode>,
+    /// WebP only, 0-100.
+    pub quality: Option<u8>,
+    /// Clip start in seconds.
+    pub start_time: Option<f64>,
+    /// Clip end in seconds.
+    pub end_time: Option<f64>,
+    /// Overrides the collision policy from settings, e.g. to retry after `output_exists`.
+    #[serde(default)]
+    pub collision_policy: Option<CollisionPolicy>,
+}
+
+/// Starts exporting a video clip as an animated GIF or WebP.
+///
+/// Returns the job ID; progress and completion are reported through the usual
+/// `conversion_progress` and `conversion_complete` events.
+#[tauri::command]
+pub async fn convert_to_animation(
+    file_path: String,
+    output_path: String,
+    options: AnimationOptions,
+    app_handle: AppHandle,
+) -> Result<String, FileDittoError> {
+    validate_output_extension(&output_path, options.format)?;
+    validate_frame_options(&options)?;
+    ffmpeg::ensure_features_supported(&app_handle, options.format.required_features())?;
+
+    let (job, output_path) = JobContext::register_output(
+        &app_handle,
+        &[&file_path],
+        &output_path,
+        CollisionSettings::load(&app_handle).with_policy(options.collision_policy),
+    )?;
+    let conversion_id = job.id.clone();
+
+    info!(
+        "Starting {:?} export: {} -> {} (Process ID: {})",
+        options.format,
+        file_path,
+        output_path,
+        job.short_id()
+    );
+
+    job::spawn_job(job, move |job| async move {
+        perform_animation(&file_path, &output_path, &options, &job).await
+    });
+
+    Ok(conversion_id)
+}
+
+async fn perform_animation(
+    input_path: &str,
+    output_path: &str,
+    options: &AnimationOptions,
+    job: &JobContext,
+) -> Result<String> {
+    let info = metadata::probe_media_info(input_path)?;
+    let (start, clip_duration) = resolve_time_range(options, info.duration)?;
+
+    let mut cmd = Command::new(path::ffmpeg_path());
+    cmd.arg("-y");
+    if let Some(start) = start {
+        cmd.args(["-ss", &format!("{:.3}", start)]);
+    }
+    if let (Some(_), Some(duration)) = (options.end_time, clip_duration) {
+        cmd.args(["-t", &format!("{:.3}", duration)]);
+    }
+    cmd.args(["-i", input_path]);
+    cmd.args(build_animation_args(options));
+    let output = AtomicOutput::new(output_path, job.short_id())?;
+    cmd.arg(output.temp_path());
+
+    job.run_ffmpeg(&mut cmd, clip_duration, (0.0, 100.0))?;
+    output.commit()?;
+
+    Ok(output_path.to_string())
+}
+
+/// Validates the requested clip range and returns its start and length in seconds.
+fn resolve_time_range(
+    options: &AnimationOptions,
+    source_duration: Option<f64>,
+) -> Result<(Option<f64>, Option<f64>)> {
+    let start = options.start_time.filter(|s| *s > 0.0);
+    let end = options.end_time.or(source_duration);
+
+    if let (Some(end), Some(source_duration)) = (options.end_time, source_duration) {
+        if end > source_duration {
+            return Err(FileDittoError::InvalidOptions(
+                format!(
+                    "Clip end ({:.2}s) is past the end of the video ({:.2}s)",
+                    end, source_duration
+                )
+                .into(),
+            )
+            .into());
+        }
+    }
+    if let (Some(start), Some(end)) = (start, end) {
+        if start >= end {
+            return Err(FileDittoError::InvalidOptions(
+                format!(
+                    "Clip start ({:.2}s) must be before its end ({:.2}s)",
+                    start, end
+                )
+                .into(),
+            )
+            .into());
+        }
+    }
+
+    let duration = end.map(|end| end - start.unwrap_or(0.0));
+    Ok((start, duration))
+}
+
+/// Rejects a zero frame rate or width, which FFmpeg's filters cannot use.
+fn validate_frame_options(options: &AnimationOptions) -> Result<(), FileDittoError> {
+    if options.fps == Some(0) {
+        return Err(FileDittoError::InvalidOptions(
+            "Frame rate must be at least 1".into(),
+        ));
+    }
+    if options.width == Some(0) {
+        return Err(FileDittoError::InvalidOptions(
+            "Width must be at least 1 pixel".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Builds the encoder and filter arguments for the animated output.
+fn build_animation_args(options: &AnimationOptions) -> Vec<String> {
+    let fps = options.fps.unwrap_or(DEFAULT_FPS);
+    let width = options.width.unwrap_or(DEFAULT_WIDTH);
+    let loop_count = options.loop_count.unwrap_or(0).to_string();
+    let base_filters = format!("fps={},scale={}:-1:flags=lanczos", fps, width);
+
+    match options.format {
+        AnimationFormat::Gif => {
+            let dither = options.dither.unwrap_or_default().as_filter_value();
+            vec![
+                "-filter_complex".to_string(),
+                format!(
+                    "[0:v]{},split[a][b];[a]palettegen=stats_mode=diff[p];[b][p]paletteuse=dither={}:diff_mode=rectangle",
+                    base_filters, dither
+                ),
+                "-loop".to_string(),
+                loop_count,
+            ]
+        }
+        AnimationFormat::Webp => {
+            let quality = options.quality.unwrap_or(75).min(100).to_string();
+            vec![
+                "-vf".to_string(),
+                base_filters,
+                "-c:v".to_string(),
+                "libwebp".to_string(),
+                "-lossless".to_string(),
+                "0".to_string(),
+                "-quality".to_string(),
+                quality,
+                "-loop".to_string(),
+                loop_count,
+                "-an".to_string(),
+            ]
+        }
+    }
+}
+
+/// Ensures the output path extension matches the requested animation format.
+fn validate_output_extension(output_path: &str, format: AnimationFormat) -> Result<()> {
+    let extension = Path::new(output_path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let expected = match format {
+        AnimationFormat::Gif => "gif",
+        AnimationFormat::Webp => "webp",
+    };
+
+    if extension != expected {
+        return Err(FileDittoError::InvalidOptions(
+            format!(
+                "Output path must end in .{} for {:?} output: {}",
+                expected, format, output_path
+            )
+            .into(),
+        )
+        .into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,338 @@
+//! Joining multiple media files into a single output.
+//!
+//! Inputs whose streams share codec parameters are joined with the concat
+//! demuxer and stream copy, which is lossless and fast. Anything else goes
+//! through the concat filter and is re-encoded to the output format.
+
+use crate::conversion_settings;
+use crate::error::FileDittoError;
+use crate::job::{self, JobContext};
+use crate::metadata::{self, MediaInfo};
+use crate::output::AtomicOutput;
+use crate::path;
+use crate::settings::{CollisionPolicy, CollisionSettings};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tauri::AppHandle;
+use tracing::info;
+
+/// Audio encoders for joining audio-only inputs: output extension, encoder
+/// and whether it takes a bitrate.
+const AUDIO_ENCODERS: &[(&str, &str, bool)] = &[
+    ("mp3", "libmp3lame", true),
+    ("m4a", "aac", true),
+    ("aac", "aac", true),
+    ("ogg", "libvorbis", true),
+    ("opus", "libopus", true),
+    ("wav", "pcm_s16le", false),
+    ("flac", "flac", false),
+];
+
+/// Options for joining files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConcatOptions {
+    /// Quality used when the inputs have to be re-encoded.
+    pub quality: String,
+    /// Always re-encode, even when stream copy would be possible.
+    #[serde(default)]
+    pub force_reencode: bool,
+    /// Overrides the collision policy from settings, e.g. to retry after `output_exists`.
+    #[serde(default)]
+    pub collision_policy: Option<CollisionPolicy>,
+}
+
+/// Starts joining `file_paths`, in order, into `output_path`.
+///
+/// Returns the job ID; progress across the combined duration and completion
+/// are reported through the usual `conversion_progress` and
+/// `conversion_complete` events.
+#[tauri::command]
+pub async fn concat_files(
+    file_paths: Vec<String>,
+    output_path: String,
+    options: ConcatOptions,
+    app_handle: AppHandle,
+) -> Result<String, FileDittoError> {
+    if file_paths.len() < 2 {
+        return Err(FileDittoError::InvalidOptions(
+            "At least two files are needed to concatenate".into(),
+        ));
+    }
+
+    let (job, output_path) = JobContext::register_output(
+        &app_handle,
+        &file_paths,
+        &output_path,
+        CollisionSettings::load(&app_handle).with_policy(options.collision_policy),
+    )?;
+    let conversion_id = job.id.clone();
+
+    info!(
+        "Starting concatenation of {} files -> {} (Process ID: {})",
+        file_paths.len(),
+        output_path,
+        job.short_id()
+    );
+
+    job::spawn_job(job, move |job| async move {
+        perform_concat(&file_paths, &output_path, &options, &job).await
+    });
+
+    Ok(conversion_id)
+}
+
+async fn perform_concat(
+    input_paths: &[String],
+    output_path: &str,
+    options: &ConcatOptions,
+    job: &JobContext,
+) -> Result<String> {
+    let infos = input_paths
+        .iter()
+        .map(|p| metadata::probe_media_info(p))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Missing durations only make the progress bar less accurate.
+    let total_duration: f64 = infos.iter().filter_map(|i| i.duration).sum();
+    let total_duration = Some(total_duration).filter(|d| *d > 0.0);
+
+    let mut cmd = Command::new(path::ffmpeg_path());
+    let list_path = std::env::temp_dir().join(format!("fileditto_concat_{}.txt", job.id));
+
+    if !options.force_reencode && streams_match(&infos) {
+        info!("Inputs share codec parameters, using stream copy");
+        fs::write(&list_path, build_concat_list(input_paths))
+            .map_err(|e| FileDittoError::from_io(&e, "Failed to write concat list"))?;
+        cmd.args(["-y", "-f", "concat", "-safe", "0", "-i"]);
+        cmd.arg(&list_path);
+        // Data and attachment streams often cannot be muxed into the output
+        cmd.args(["-map", "0:v?", "-map", "0:a?", "-map", "0:s?", "-c", "copy"]);
+    } else {
+        info!("Inputs differ, re-encoding with the concat filter");
+        cmd.arg("-y");
+        for input_path in input_paths {
+            cmd.args(["-i", input_path]);
+        }
+        let audio_only = infos.iter().all(|info| info.width.is_none());
+        let (filter, maps) = if audio_only {
+            build_audio_concat_filter(&infos)?
+        } else {
+            build_concat_filter(&infos)?
+        };
+        cmd.args(["-filter_complex", &filter]);
+        for map in maps {
+            cmd.args(["-map", &map]);
+        }
+
+        let output_format = Path::new(output_path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if audio_only {
+            cmd.args(audio_encoder_args(&output_format, &options.quality)?);
+        } else {
+            conversion_settings::get_format_config(&output_format, &options.quality)?
+                .apply_to_command(&mut cmd);
+        }
+    }
+    let output = AtomicOutput::new(output_path, job.short_id())?;
+    cmd.arg(output.temp_path());
+
+    let result = job.run_ffmpeg(&mut cmd, total_duration, (0.0, 100.0));
+    let _ = fs::remove_file(&list_path);
+    result?;
+
+    output.commit()?;
+    Ok(output_path.to_string())
+}
+
+/// Returns true if every input has the same streams with the same codec parameters.
+fn streams_match(infos: &[MediaInfo]) -> bool {
+    infos
+        .windows(2)
+        .all(|pair| !pair[0].streams.is_empty() && pair[0].streams == pair[1].streams)
+}
+
+/// Builds the concat demuxer list file contents.
+fn build_concat_list(input_paths: &[String]) -> String {
+    input_paths
+        .iter()
+        .map(|p| format!("file '{}'\n", p.replace('\'', "'\\''")))
+        .collect()
+}
+
+/// Builds a concat filter graph for inputs without video, resampling every
+/// input to 48 kHz stereo. Returns the graph and its output label.
+fn build_audio_concat_filter(infos: &[MediaInfo]) -> Result<(String, Vec<String>)> {
+    if !infos.iter().all(MediaInfo::has_audio) {
+        return Err(FileDittoError::UnsupportedFormat(
+            "All inputs must contain a video or audio stream".into(),
+        )
+        .into());
+    }
+
+    let mut graph = String::new();
+    let mut segments = String::new();
+    for i in 0..infos.len() {
+        graph.push_str(&format!(
+            "[{i}:a:0]aresample=48000,aformat=channel_layouts=stereo[a{i}];"
+        ));
+        segments.push_str(&format!("[a{i}]"));
+    }
+    graph.push_str(&format!(
+        "{}concat=n={}:v=0:a=1[outa]",
+        segments,
+        infos.len()
+    ));
+
+    Ok((graph, vec!["[outa]".to_string()]))
+}
+
+/// Encoder arguments for a re-encoded audio-only output.
+fn audio_encoder_args(output_format: &str, quality: &str) -> Result<Vec<String>> {
+    let (codec, lossy) = AUDIO_ENCODERS
+        .iter()
+        .find(|(format, _, _)| *format == output_format)
+        .map(|(_, codec, lossy)| (*codec, *lossy))
+        .ok_or_else(|| {
+            FileDittoError::UnsupportedFormat(
+                format!(
+                    "Unsupported output format for joining audio: '{}'",
+                    output_format
+                )
+                .into(),
+            )
+        })?;
+
+    let mut args = vec!["-vn".to_string(), "-c:a".to_string(), codec.to_string()];
+    if lossy {
+        let bitrate = match quality {
+            "high" => "256k",
+            "low" => "128k",
+            _ => "192k",
+        };
+        args.extend(["-b:a".to_string(), bitrate.to_string()]);
+    }
+    Ok(args)
+}
+
+/// Builds a concat filter graph that normalises every input to the first
+/// input's resolution and frame rate. Returns the graph and its output labels.
+fn build_concat_filter(infos: &[MediaInfo]) -> Result<(String, Vec<String>)> {
+    let first = &infos[0];
+    let (width, height) = match (first.width, first.height) {
+        (Some(w), Some(h)) => (w / 2 * 2, h / 2 * 2),
+        _ => {
+            return Err(FileDittoError::UnsupportedFormat(
+                "First input has no video stream to concatenate".into(),
+            )
+            .into())
+        }
+    };
+    let frame_rate = first
+        .streams
+        .iter()
+        .find(|s| s.codec_type == "video")
+        .and_then(|s| s.frame_rate.clone())
+        .unwrap_or_else(|| "30".to_string());
+
+    if infos.iter().any(|i| i.width.is_none()) {
+        return Err(FileDittoError::UnsupportedFormat(
+            "All inputs must contain a video stream".into(),
+        )
+        .into());
+    }
+    // The concat filter needs the same streams from every segment, so audio is
+    // only kept when all inputs have it.
+    let with_audio = infos.iter().all(MediaInfo::has_audio);
+
+    let mut graph = String::new();
+    let mut segments = String::new();
+    for i in 0..infos.len() {
+        graph.push_str(&format!(
+            "[{i}:v:0]scale={width}:{height}:force_original_aspect_ratio=decrease,\
+             pad={width}:{height}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={frame_rate},format=yuv420p[v{i}];"
+        ));
+        segments.push_str(&format!("[v{}]", i));
+        if with_audio {
+            graph.push_str(&format!(
+                "[{i}:a:0]aresample=48000,aformat=channel_layouts=stereo[a{i}];"
+            ));
+            segments.push_str(&format!("[a{i}]"));
+        }
+    }
+
+    let audio_count = if with_audio { 1 } else { 0 };
+    graph.push_str(&format!(
+        "{}concat=n={}:v=1:a={}[outv]",
+        segments,
+        infos.len(),
+        audio_count
+    ));
+
+    let mut maps = vec!["[outv]".to_string()];
+    if with_audio {
+        graph.push_str("[outa]");
+        maps.push("[outa]".to_string());
+    }
+
+    Ok((graph, maps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::StreamInfo;
+
+    fn audio_info() -> MediaInfo {
+        MediaInfo {
+            duration: Some(60.0),
+            streams: vec![StreamInfo {
+                codec_type: "audio".to_string(),
+                codec_name: Some("mp3".to_string()),
+                profile: None,
+                width: None,
+                height: None,
+                pix_fmt: None,
+                frame_rate: None,
+                sample_rate: Some("44100".to_string()),
+                channels: Some(2),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn audio_only_inputs_join_without_video() {
+        let (graph, maps) = build_audio_concat_filter(&[audio_info(), audio_info()]).unwrap();
+
+        assert_eq!(
+            graph,
+            "[0:a:0]aresample=48000,aformat=channel_layouts=stereo[a0];\
+             [1:a:0]aresample=48000,aformat=channel_layouts=stereo[a1];\
+             [a0][a1]concat=n=2:v=0:a=1[outa]"
+        );
+        assert_eq!(maps, ["[outa]"]);
+    }
+
+    #[test]
+    fn inputs_without_any_stream_are_rejected() {
+        assert!(build_audio_concat_filter(&[audio_info(), MediaInfo::default()]).is_err());
+    }
+
+    #[test]
+    fn lossless_audio_takes_no_bitrate() {
+        assert_eq!(
+            audio_encoder_args("mp3", "high").unwrap(),
+            ["-vn", "-c:a", "libmp3lame", "-b:a", "256k"]
+        );
+        assert_eq!(
+            audio_encoder_args("flac", "high").unwrap(),
+            ["-vn", "-c:a", "flac"]
+        );
+        assert!(audio_encoder_args("mkv", "high").is_err());
+    }
+}
@@ -1,15 +1,21 @@
 //! File conversion functionality using FFmpeg.
 
 use crate::conversion_settings;
+use crate::error::FileDittoError;
+use crate::ffmpeg;
+use crate::filters::{self, FilterPlan};
+use crate::job::{self, JobContext};
+use crate::metadata::{self, MediaInfo};
+use crate::output::AtomicOutput;
 use crate::path;
-use crate::types::{
-    ConversionOptions, ConversionProgress, ConversionResult, ConversionState, ProcessHandles,
-};
-use anyhow::{anyhow, Result};
+use crate::presets;
+use crate::settings::CollisionSettings;
+use crate::types::{ConversionOptions, ConversionProgress, ConversionState, ProcessHandles};
+use anyhow::Result;
 use std::path::Path;
-use std::process::{Command, Stdio};
+use std::process::Command;
 use tauri::{AppHandle, Emitter, Manager};
-use uuid::Uuid;
+use tracing::{debug, error, info, warn};
 
 // Main conversion process
 #[tauri::command]
@@ -18,12 +24,29 @@ pub async fn convert_file(
     output_path: String,
     options: ConversionOptions,
     app_handle: AppHandle,
-) -> Result<String, String> {
-    // Generate a unique conversion ID
-    let conversion_id = Uuid::new_v4().to_string();
+) -> Result<String, FileDittoError> {
+    // Reject missing encoders up front instead of as "Unknown encoder" mid-run
+    let video_codec = match &options.preset {
+        Some(preset_id) => presets::get_preset(preset_id).map(|preset| preset.video_codec)?,
+        None => {
+            conversion_settings::get_format_config_for_codec(
+                &options.output_format,
+                &options.quality,
+                options.video_codec.as_deref(),
+            )?
+            .video_codec
+        }
+    };
+    ffmpeg::ensure_encoder_available(&app_handle, video_codec)?;
+    ffmpeg::ensure_features_supported(&app_handle, &filters::required_features(&options))?;
+
+    let collision = CollisionSettings::load(&app_handle).with_policy(options.collision_policy);
+    let (job, output_path) =
+        JobContext::register_output(&app_handle, &[&file_path], &output_path, collision)?;
+    let conversion_id = job.id.clone();
 
-    println!(
-        "🚀 Starting conversion: {} -> {} (Process ID: {})",
+    info!(
+        "Starting conversion: {} -> {} (Process ID: {})",
         Path::new(&file_path)
             .file_name()
             .unwrap_or_default()
@@ -32,52 +55,11 @@ pub async fn convert_file(
             .file_name()
             .unwrap_or_default()
             .to_string_lossy(),
-        &conversion_id[..8]
+        job.short_id()
     );
 
-    // Initialize conversion state
-    let state: ConversionState = app_handle.state::<ConversionState>().inner().clone();
-    {
-        let mut conversions = state.lock().unwrap();
-        conversions.insert(
-            conversion_id.clone(),
-            ConversionProgress {
-                id: conversion_id.clone(),
-                progress: 0.0,
-                status: "Starting".to_string(),
-                current_file: file_path.clone(),
-                output_path: Some(output_path.clone()),
-                eta: None,
-                speed: None,
-            },
-        );
-    }
-
-    let conversion_id_clone = conversion_id.clone();
-    let app_handle_clone = app_handle.clone();
-    let app_handle_for_conversion = app_handle.clone();
-
-    // Spawn conversion task
-    tokio::spawn(async move {
-        let result = perform_conversion(
-            &file_path,
-            &output_path,
-            &options,
-            &conversion_id_clone,
-            state,
-            app_handle_for_conversion,
-        )
-        .await;
-
-        // Emit final result
-        let conversion_result = ConversionResult {
-            id: conversion_id_clone.clone(),
-            success: result.is_ok(),
-            output_path: result.as_ref().ok().cloned(),
-            error: result.as_ref().err().map(|e| e.to_string()),
-        };
-
-        let _ = app_handle_clone.emit("conversion_complete", conversion_result);
+    job::spawn_job(job, move |job| async move {
+        perform_conversion(&file_path, &output_path, &options, &job).await
     });
 
     Ok(conversion_id)
@@ -99,24 +81,25 @@ pub async fn get_conversion_progress(
 pub async fn cancel_conversion(
     conversion_id: String,
     app_handle: AppHandle,
-) -> Result<bool, String> {
-    println!("🛑 Cancelling conversion: {}", &conversion_id[..8]);
+) -> Result<bool, FileDittoError> {
+    info!("Cancelling conversion: {}", &conversion_id[..8]);
 
-    // Update status to cancelling first and get output path for cleanup
-    let output_path_for_cleanup: Option<String>;
+    // Update status to cancelling first. The worker removes its temporary
+    // output once FFmpeg exits; the final output path is never touched here.
     let state: ConversionState = app_handle.state::<ConversionState>().inner().clone();
     {
         let mut conversions = state.lock().unwrap();
         if let Some(progress) = conversions.get_mut(&conversion_id) {
             progress.status = "Cancelling".to_string();
-            output_path_for_cleanup = progress.output_path.clone();
-            println!(
-                "📊 Updated status to 'Cancelling' for conversion: {}",
+            info!(
+                "Updated status to 'Cancelling' for conversion: {}",
                 &conversion_id[..8]
             );
             let _ = app_handle.emit("conversion_progress", progress.clone());
         } else {
-            return Err("Conversion not found".to_string());
+            return Err(FileDittoError::InvalidOptions(
+                "Conversion not found".into(),
+            ));
         }
     }
 
@@ -129,48 +112,30 @@ pub async fn cancel_conversion(
             {
                 use std::process::Command;
                 match Command::new("taskkill")
-                    .args(&["/F", "/PID", &process_id.to_string()])
+                    .args(["/F", "/PID", &process_id.to_string()])
                     .output()
                 {
                     Ok(output) => {
                         if output.status.success() {
-                            println!(
-                                "✅ FFmpeg process killed successfully for conversion: {}",
+                            info!(
+                                "FFmpeg process killed successfully for conversion: {}",
                                 &conversion_id[..8]
                             );
 
-                            // Clean up partial output file
-                            if let Some(output_path) = &output_path_for_cleanup {
-                                if Path::new(output_path).exists() {
-                                    match std::fs::remove_file(output_path) {
-                                        Ok(_) => {
-                                            println!(
-                                                "🧹 Removed partial output file: {}",
-                                                output_path
-                                            );
-                                        }
-                                        Err(e) => {
-                                            println!(
-                                                "⚠️ Failed to remove partial output file: {} - {}",
-                                                output_path, e
-                                            );
-                                        }
-                                    }
-                                } else {
-                                    println!("ℹ️ No partial output file to clean up");
-                                }
-                            }
-
                             Ok(true)
                         } else {
                             let error = String::from_utf8_lossy(&output.stderr);
-                            println!("❌ Failed to kill FFmpeg process: {}", error);
-                            Err(format!("Failed to kill process: {}", error))
+                            error!("Failed to kill FFmpeg process: {}", error);
+                            Err(FileDittoError::Internal(
+                                format!("Failed to kill process: {}", error).into(),
+                            ))
                         }
                     }
                     Err(e) => {
-                        println!("❌ Failed to execute taskkill: {}", e);
-                        Err(format!("Failed to execute taskkill: {}", e))
+                        error!("Failed to execute taskkill: {}", e);
+                        Err(FileDittoError::Internal(
+                            format!("Failed to execute taskkill: {}", e).into(),
+                        ))
                     }
                 }
             }
@@ -178,54 +143,36 @@ pub async fn cancel_conversion(
             {
                 use std::process::Command;
                 match Command::new("kill")
-                    .args(&["-9", &process_id.to_string()])
+                    .args(["-9", &process_id.to_string()])
                     .output()
                 {
                     Ok(output) => {
                         if output.status.success() {
-                            println!(
-                                "✅ FFmpeg process killed successfully for conversion: {}",
+                            info!(
+                                "FFmpeg process killed successfully for conversion: {}",
                                 &conversion_id[..8]
                             );
 
-                            // Clean up partial output file
-                            if let Some(output_path) = &output_path_for_cleanup {
-                                if Path::new(output_path).exists() {
-                                    match std::fs::remove_file(output_path) {
-                                        Ok(_) => {
-                                            println!(
-                                                "🧹 Removed partial output file: {}",
-                                                output_path
-                                            );
-                                        }
-                                        Err(e) => {
-                                            println!(
-                                                "⚠️ Failed to remove partial output file: {} - {}",
-                                                output_path, e
-                                            );
-                                        }
-                                    }
-                                } else {
-                                    println!("ℹ️ No partial output file to clean up");
-                                }
-                            }
-
                             Ok(true)
                         } else {
                             let error = String::from_utf8_lossy(&output.stderr);
-                            println!("❌ Failed to kill FFmpeg process: {}", error);
-                            Err(format!("Failed to kill process: {}", error))
+                            error!("Failed to kill FFmpeg process: {}", error);
+                            Err(FileDittoError::Internal(
+                                format!("Failed to kill process: {}", error).into(),
+                            ))
                         }
                     }
                     Err(e) => {
-                        println!("❌ Failed to execute kill: {}", e);
-                        Err(format!("Failed to execute kill: {}", e))
+                        error!("Failed to execute kill: {}", e);
+                        Err(FileDittoError::Internal(
+                            format!("Failed to execute kill: {}", e).into(),
+                        ))
                     }
                 }
             }
         } else {
-            println!(
-                "⚠️ Process not found or already completed for conversion: {}",
+            warn!(
+                "Process not found or already completed for conversion: {}",
                 &conversion_id[..8]
             );
             // Still return Ok(true) since the conversion is effectively "cancelled"
@@ -239,181 +186,133 @@ async fn perform_conversion(
     input_path: &str,
     output_path: &str,
     options: &ConversionOptions,
-    conversion_id: &str,
-    state: ConversionState,
-    app_handle: AppHandle,
+    job: &JobContext,
 ) -> Result<String> {
-    println!(
-        "🔍 Starting conversion process for ID: {}",
-        &conversion_id[..8]
-    );
-    println!("📁 Input file: {}", input_path);
-    println!("📁 Output file: {}", output_path);
-    println!("⚙️ Options: {:?}", options);
+    debug!("Starting conversion process for ID: {}", job.short_id());
+    debug!("Input file: {}", input_path);
+    debug!("Output file: {}", output_path);
+    info!("Options: {:?}", options);
 
     // Validate input file exists
     if !Path::new(input_path).exists() {
         let error_msg = format!("Input file does not exist: {}", input_path);
-        println!("❌ {}", error_msg);
-        return Err(anyhow!(error_msg));
+        error!("{}", error_msg);
+        return Err(FileDittoError::InputMissing(error_msg.into()).into());
     }
 
+    // Probe data only refines progress reporting, so a failed probe is not fatal
+    let info = metadata::probe_media_info(input_path).ok();
+
     let ffmpeg_path = path::ffmpeg_path();
-    println!("🔧 Using FFmpeg path: {}", ffmpeg_path.display());
+    debug!("Using FFmpeg path: {}", ffmpeg_path.display());
 
     // Build FFmpeg command based on output format
+    let filter_plan = FilterPlan::from_options(options)?;
+    // Progress is measured on the output timeline, which speed changes stretch or shrink
+    let duration = filter_plan.output_duration(info.as_ref().and_then(|info| info.duration));
     let mut cmd = Command::new(&ffmpeg_path);
-    cmd.args(&["-y", "-i", input_path]);
+    cmd.arg("-y");
+    filter_plan.apply_input_options(&mut cmd);
+    cmd.args(["-i", input_path]);
+    filter_plan.apply_inputs(&mut cmd);
 
     // Add format-specific arguments
-    println!("🎬 Applying format settings for: {}", options.output_format);
-    apply_format_settings(&mut cmd, options)?;
+    debug!("Applying format settings for: {}", options.output_format);
+    apply_format_settings(&mut cmd, options, info.as_ref(), duration)?;
+    filter_plan.apply_filters(&mut cmd);
 
     // Add metadata preservation option
     if !options.preserve_metadata {
-        cmd.args(&["-map_metadata", "-1"]);
-        println!("🔄 Metadata preservation: disabled");
+        cmd.args(["-map_metadata", "-1"]);
+        debug!("Metadata preservation: disabled");
     } else {
-        println!("🔄 Metadata preservation: enabled");
+        debug!("Metadata preservation: enabled");
     }
 
-    cmd.arg(output_path);
-    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    // Write to a temporary sibling so the final path never holds a partial file
+    let output = AtomicOutput::new(output_path, job.short_id())?;
+    cmd.arg(output.temp_path());
 
-    // Log the complete command being executed
-    let command_str = format!("{:?}", cmd);
-    println!("🚀 Executing FFmpeg command: {}", command_str);
-
-    // Start FFmpeg process
-    let child = cmd.spawn().map_err(|e| {
-        let error_msg = format!("Failed to start FFmpeg process: {}", e);
-        println!("❌ {}", error_msg);
-        println!("💡 Check if FFmpeg is properly installed and accessible");
-        anyhow!(error_msg)
-    })?;
-
-    // Store process ID for potential cancellation
-    let process_id = child.id();
-    let process_handles: ProcessHandles = app_handle.state::<ProcessHandles>().inner().clone();
-    {
-        let mut handles = process_handles.lock().unwrap();
-        handles.insert(conversion_id.to_string(), process_id);
-    }
-
-    // Update status to converting
-    {
-        let mut conversions = state.lock().unwrap();
-        if let Some(conv) = conversions.get_mut(conversion_id) {
-            conv.status = "Converting".to_string();
-            let _ = app_handle.emit("conversion_progress", conv.clone());
-        }
-    }
+    // Run FFmpeg, publishing progress as it goes
+    debug!("Waiting for FFmpeg process to complete...");
+    job.run_ffmpeg(&mut cmd, duration, (0.0, 100.0))?;
 
-    // Wait for FFmpeg process to complete
-    println!("⏳ Waiting for FFmpeg process to complete...");
+    output.commit()?;
+    debug!("Output file location: {}", output_path);
 
-    let output = child.wait_with_output().map_err(|e| {
-        let error_msg = format!("FFmpeg process failed to complete: {}", e);
-        println!("❌ {}", error_msg);
-        anyhow!(error_msg)
-    })?;
-
-    println!(
-        "🎯 FFmpeg process completed with exit code: {:?}",
-        output.status.code()
-    );
-
-    if !output.status.success() {
-        let stderr_output = String::from_utf8_lossy(&output.stderr);
-        let stdout_output = String::from_utf8_lossy(&output.stdout);
-
-        println!("❌ FFmpeg conversion failed!");
-        println!("📊 Exit code: {:?}", output.status.code());
-        println!("📄 STDERR output:\n{}", stderr_output);
-        println!("📄 STDOUT output:\n{}", stdout_output);
-
-        // Try to provide more specific error context
-        let error_context = if stderr_output.contains("No such file or directory") {
-            "Input file not found or inaccessible"
-        } else if stderr_output.contains("Permission denied") {
-            "Permission denied - check file/directory permissions"
-        } else if stderr_output.contains("Invalid argument") {
-            "Invalid FFmpeg arguments or unsupported codec"
-        } else if stderr_output.contains("Conversion failed") {
-            "FFmpeg codec conversion failed"
-        } else if stderr_output.contains("Unknown encoder") {
-            "Unsupported encoder for this format"
-        } else {
-            "General FFmpeg error"
-        };
-
-        println!("💡 Error context: {}", error_context);
-
-        return Err(anyhow!(
-            "FFmpeg conversion failed: {} - {}",
-            error_context,
-            stderr_output.trim()
-        ));
-    }
+    Ok(output_path.to_string())
+}
 
-    // Verify output file was created successfully
+/// Verifies an FFmpeg output file exists and is not empty.
+pub fn verify_output_file(output_path: &str) -> Result<u64> {
     let output_file = Path::new(output_path);
     if !output_file.exists() {
         let error_msg = format!("Output file was not created: {}", output_path);
-        println!("❌ {}", error_msg);
-        return Err(anyhow!(error_msg));
+        error!("{}", error_msg);
+        return Err(FileDittoError::FfmpegFailed(error_msg.into()).into());
     }
 
     let file_size = output_file.metadata().map(|m| m.len()).unwrap_or(0);
 
     if file_size == 0 {
         let error_msg = format!("Output file is empty: {}", output_path);
-        println!("❌ {}", error_msg);
-        return Err(anyhow!(error_msg));
+        error!("{}", error_msg);
+        return Err(FileDittoError::FfmpegFailed(error_msg.into()).into());
     }
 
-    println!(
-        "✅ Conversion completed successfully: {} ({} bytes)",
+    info!(
+        "Output written successfully: {} ({} bytes)",
         output_file
             .file_name()
             .unwrap_or_default()
             .to_string_lossy(),
         file_size
     );
-    println!("📁 Output file location: {}", output_path);
-
-    // Remove completed conversion from tracking
-    {
-        let mut conversions = state.lock().unwrap();
-        conversions.remove(conversion_id);
-    }
-
-    // Remove process handle from tracking
-    {
-        let process_handles: ProcessHandles = app_handle.state::<ProcessHandles>().inner().clone();
-        let mut handles = process_handles.lock().unwrap();
-        handles.remove(conversion_id);
-    }
 
-    Ok(output_path.to_string())
+    Ok(file_size)
 }
 
 /// Applies format-specific FFmpeg settings based on the conversion options.
-fn apply_format_settings(cmd: &mut Command, options: &ConversionOptions) -> Result<()> {
-    println!(
-        "🎨 Configuring format settings for: {}",
-        options.output_format
-    );
+///
+/// `output_duration` is the converted length, used by size-limited presets.
+fn apply_format_settings(
+    cmd: &mut Command,
+    options: &ConversionOptions,
+    info: Option<&MediaInfo>,
+    output_duration: Option<f64>,
+) -> Result<()> {
+    info!("Configuring format settings for: {}", options.output_format);
+
+    if let Some(preset_id) = &options.preset {
+        let preset = presets::get_preset(preset_id)?;
+        if preset.container != options.output_format {
+            return Err(FileDittoError::InvalidOptions(
+                format!(
+                    "The {} preset produces .{} files, not .{}",
+                    preset.name, preset.container, options.output_format
+                )
+                .into(),
+            )
+            .into());
+        }
+        preset.apply_to_command(cmd, info, output_duration)?;
+        info!("Device preset: {}", preset.name);
+        return Ok(());
+    }
 
-    let config = conversion_settings::get_format_config(&options.output_format, &options.quality)?;
+    let config = conversion_settings::get_format_config_for_codec(
+        &options.output_format,
+        &options.quality,
+        options.video_codec.as_deref(),
+    )?;
 
     config.apply_to_command(cmd);
 
-    println!(
-        "📊 Quality: {} for format: {}",
-        options.quality, options.output_format
+    info!(
+        "Quality: {} for format: {} ({})",
+        options.quality, options.output_format, config.video_codec
     );
-    println!("✅ Format settings applied successfully");
+    info!("Format settings applied successfully");
 
     Ok(())
 }
@@ -1,4 +1,5 @@
-use anyhow::{anyhow, Result};
+use crate::error::FileDittoError;
+use anyhow::Result;
 use std::process::Command;
 
 #[derive(Debug, Clone)]
@@ -8,33 +9,152 @@ pub struct FormatConfig {
     pub preset: Option<&'static str>,
     pub crf: Option<&'static str>,
     pub bitrate: Option<&'static str>,
+    /// Additional encoder-specific arguments, appended after the common ones.
+    pub extra_args: &'static [&'static str],
 }
 
 impl FormatConfig {
     /// Apply this configuration to an FFmpeg command
     pub fn apply_to_command(&self, cmd: &mut Command) {
         // Apply video codec
-        cmd.args(&["-c:v", self.video_codec]);
+        cmd.args(["-c:v", self.video_codec]);
 
         // Apply audio codec if specified
         if let Some(audio_codec) = self.audio_codec {
-            cmd.args(&["-c:a", audio_codec]);
+            cmd.args(["-c:a", audio_codec]);
         }
 
         // Apply preset if specified
         if let Some(preset) = self.preset {
-            cmd.args(&["-preset", preset]);
+            cmd.args(["-preset", preset]);
         }
 
         // Apply CRF if specified
         if let Some(crf) = self.crf {
-            cmd.args(&["-crf", crf]);
+            cmd.args(["-crf", crf]);
         }
 
         // Apply bitrate if specified
         if let Some(bitrate) = self.bitrate {
-            cmd.args(&["-b:v", bitrate]);
+            cmd.args(["-b:v", bitrate]);
         }
+
+        // Apply encoder-specific arguments
+        cmd.args(self.extra_args);
+    }
+}
+
+/// Video encoders users can choose between, in order of preference.
+pub const VIDEO_CODECS: &[&str] = &[
+    "libx264",
+    "libx265",
+    "libvpx-vp9",
+    "libsvtav1",
+    "libaom-av1",
+];
+
+/// Returns the video encoders a container can hold.
+pub fn supported_video_codecs(format: &str) -> &'static [&'static str] {
+    match format {
+        "mp4" => &[
+            "libx264",
+            "libx265",
+            "libvpx-vp9",
+            "libsvtav1",
+            "libaom-av1",
+        ],
+        "webm" => &["libvpx-vp9", "libsvtav1", "libaom-av1"],
+        "mov" => &["libx264", "libx265"],
+        "avi" => &["libx264"],
+        _ => &[],
+    }
+}
+
+/// Get format configuration for a format, quality and optional video encoder.
+///
+/// Without an explicit encoder this is the same as [`get_format_config`].
+pub fn get_format_config_for_codec(
+    format: &str,
+    quality: &str,
+    video_codec: Option<&str>,
+) -> Result<FormatConfig> {
+    let Some(video_codec) = video_codec else {
+        return get_format_config(format, quality);
+    };
+
+    let supported = supported_video_codecs(format);
+    if supported.is_empty() {
+        // Reuse the unsupported format error
+        get_format_config(format, quality)?;
+    }
+    if !supported.contains(&video_codec) {
+        return Err(FileDittoError::UnsupportedFormat(
+            format!(
+                "Video codec '{}' cannot be used in .{} files. Supported codecs: {}",
+                video_codec,
+                format,
+                supported.join(", ")
+            )
+            .into(),
+        )
+        .into());
+    }
+
+    let audio_codec = if format == "webm" { "libopus" } else { "aac" };
+    let mut config = get_codec_config(video_codec, quality);
+    config.audio_codec = Some(audio_codec);
+
+    // Apple players only recognise HEVC in MP4/MOV with the hvc1 tag
+    if video_codec == "libx265" {
+        config.extra_args = &["-tag:v", "hvc1"];
+    }
+
+    Ok(config)
+}
+
+/// Get the quality mapping for a video encoder, independent of container
+fn get_codec_config(video_codec: &str, quality: &str) -> FormatConfig {
+    // (preset, crf) per quality level; unknown levels use medium
+    let (preset, crf) = match (video_codec, quality) {
+        ("libx264", "high") => (Some("slow"), "18"),
+        ("libx264", "low") => (Some("fast"), "28"),
+        ("libx264", _) => (Some("medium"), "23"),
+        ("libx265", "high") => (Some("slow"), "22"),
+        ("libx265", "low") => (Some("fast"), "32"),
+        ("libx265", _) => (Some("medium"), "28"),
+        ("libsvtav1", "high") => (Some("6"), "28"),
+        ("libsvtav1", "low") => (Some("10"), "40"),
+        ("libsvtav1", _) => (Some("8"), "35"),
+        ("libaom-av1", "high") => (None, "26"),
+        ("libaom-av1", "low") => (None, "38"),
+        ("libaom-av1", _) => (None, "32"),
+        // libvpx-vp9 keeps the existing WebM bitrate mapping
+        _ => {
+            let mut config = get_webm_config(quality);
+            config.video_codec = "libvpx-vp9";
+            return config;
+        }
+    };
+
+    let video_codec = VIDEO_CODECS
+        .iter()
+        .find(|c| **c == video_codec)
+        .copied()
+        .unwrap_or("libx264");
+
+    FormatConfig {
+        video_codec,
+        audio_codec: None,
+        preset,
+        crf: Some(crf),
+        bitrate: None,
+        // libaom needs an unconstrained bitrate for constant quality mode and
+        // is unusably slow at its default speed
+        extra_args: if video_codec == "libaom-av1" {
+            &["-b:v", "0", "-cpu-used", "4", "-row-mt", "1"]
+        } else {
+            &[]
+        },
     }
 }
 
@@ -46,10 +166,14 @@ pub fn get_format_config(format: &str, quality: &str) -> Result<FormatConfig> {
         "avi" => get_avi_config(),
         "mov" => get_mov_config(),
         _ => {
-            return Err(anyhow!(
-                "Unsupported output format: '{}'. Supported formats: mp4, webm, avi, mov",
-                format
-            ))
+            return Err(FileDittoError::UnsupportedFormat(
+                format!(
+                    "Unsupported output format: '{}'. Supported formats: mp4, webm, avi, mov",
+                    format
+                )
+                .into(),
+            )
+            .into())
         }
     };
 
@@ -65,6 +189,7 @@ fn get_mp4_config(quality: &str) -> FormatConfig {
             preset: Some("slow"),
             crf: Some("18"),
             bitrate: None,
+            extra_args: &[],
         },
         "medium" => FormatConfig {
             video_codec: "libx264",
@@ -72,6 +197,7 @@ fn get_mp4_config(quality: &str) -> FormatConfig {
             preset: Some("medium"),
             crf: Some("23"),
             bitrate: None,
+            extra_args: &[],
         },
         "low" => FormatConfig {
             video_codec: "libx264",
@@ -79,6 +205,7 @@ fn get_mp4_config(quality: &str) -> FormatConfig {
             preset: Some("fast"),
             crf: Some("28"),
             bitrate: None,
+            extra_args: &[],
         },
         _ => {
             // Default to medium quality for unknown quality settings
@@ -88,6 +215,7 @@ fn get_mp4_config(quality: &str) -> FormatConfig {
                 preset: Some("medium"),
                 crf: Some("23"),
                 bitrate: None,
+                extra_args: &[],
             }
         }
     }
@@ -102,6 +230,7 @@ fn get_webm_config(quality: &str) -> FormatConfig {
             preset: None,
             crf: None,
             bitrate: Some("2M"),
+            extra_args: &[],
         },
         "medium" => FormatConfig {
             video_codec: "libvpx-vp9",
@@ -109,6 +238,7 @@ fn get_webm_config(quality: &str) -> FormatConfig {
             preset: None,
             crf: None,
             bitrate: Some("1M"),
+            extra_args: &[],
         },
         "low" => FormatConfig {
             video_codec: "libvpx-vp9",
@@ -116,6 +246,7 @@ fn get_webm_config(quality: &str) -> FormatConfig {
             preset: None,
             crf: None,
             bitrate: Some("500k"),
+            extra_args: &[],
         },
         _ => {
             // Default to medium quality for unknown quality settings
@@ -125,6 +256,7 @@ fn get_webm_config(quality: &str) -> FormatConfig {
                 preset: None,
                 crf: None,
                 bitrate: Some("1M"),
+                extra_args: &[],
             }
         }
     }
@@ -138,6 +270,7 @@ fn get_avi_config() -> FormatConfig {
         preset: None,
         crf: None,
         bitrate: None,
+        extra_args: &[],
     }
 }
 
@@ -149,5 +282,6 @@ fn get_mov_config() -> FormatConfig {
         preset: None,
         crf: None,
         bitrate: None,
+        extra_args: &[],
     }
 }
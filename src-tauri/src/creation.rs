@@ -0,0 +1,317 @@
+//! Building new videos from existing media: timelapses and image slideshows.
+
+use crate::conversion_settings;
+use crate::error::FileDittoError;
+use crate::ffmpeg;
+use crate::job::{self, JobContext};
+use crate::metadata;
+use crate::output::AtomicOutput;
+use crate::path;
+use crate::settings::{CollisionPolicy, CollisionSettings};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use tauri::AppHandle;
+use tracing::info;
+
+/// Default output frame rate for timelapses and slideshows.
+const DEFAULT_FPS: u32 = 30;
+
+/// Default slideshow frame size.
+const DEFAULT_SLIDESHOW_SIZE: (u32, u32) = (1920, 1080);
+
+/// Options for building a timelapse from a long video.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelapseOptions {
+    /// Take one frame every this many seconds of source video.
+    pub interval_seconds: f64,
+    /// Playback frame rate of the timelapse.
+    pub fps: Option<u32>,
+    pub quality: String,
+    /// Overrides the collision policy from settings, e.g. to retry after `output_exists`.
+    #[serde(default)]
+    pub collision_policy: Option<CollisionPolicy>,
+}
+
+/// Options for building a slideshow video from images.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlideshowOptions {
+    /// Seconds each image is on screen, including its transitions.
+    pub image_duration: f64,
+    /// Length of the crossfade between images in seconds; 0 for hard cuts.
+    #[serde(default)]
+    pub transition_duration: f64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<u32>,
+    /// Optional soundtrack, looped or trimmed to the slideshow length.
+    pub audio_path: Option<String>,
+    pub quality: String,
+    /// Overrides the collision policy from settings, e.g. to retry after `output_exists`.
+    #[serde(default)]
+    pub collision_policy: Option<CollisionPolicy>,
+}
+
+/// Starts building a timelapse from `file_path` into `output_path`.
+///
+/// Returns the job ID; progress and completion are reported through the usual
+/// `conversion_progress` and `conversion_complete` events.
+#[tauri::command]
+pub async fn create_timelapse(
+    file_path: String,
+    output_path: String,
+    options: TimelapseOptions,
+    app_handle: AppHandle,
+) -> Result<String, FileDittoError> {
+    if options.interval_seconds <= 0.0 {
+        return Err(FileDittoError::InvalidOptions(
+            format!(
+                "Frame interval must be positive: {}",
+                options.interval_seconds
+            )
+            .into(),
+        ));
+    }
+    ffmpeg::ensure_features_supported(&app_handle, &["select"])?;
+
+    let (job, output_path) = JobContext::register_output(
+        &app_handle,
+        &[&file_path],
+        &output_path,
+        CollisionSettings::load(&app_handle).with_policy(options.collision_policy),
+    )?;
+    let conversion_id = job.id.clone();
+
+    info!(
+        "Starting timelapse: {} -> {} (Process ID: {})",
+        file_path,
+        output_path,
+        job.short_id()
+    );
+
+    job::spawn_job(job, move |job| async move {
+        perform_timelapse(&file_path, &output_path, &options, &job).await
+    });
+
+    Ok(conversion_id)
+}
+
+/// Starts building a slideshow video from `image_paths`, in order, into `output_path`.
+///
+/// Returns the job ID; progress and completion are reported through the usual
+/// `conversion_progress` and `conversion_complete` events.
+#[tauri::command]
+pub async fn create_slideshow(
+    image_paths: Vec<String>,
+    output_path: String,
+    options: SlideshowOptions,
+    app_handle: AppHandle,
+) -> Result<String, FileDittoError> {
+    validate_slideshow(&image_paths, &options)?;
+    if options.transition_duration > 0.0 {
+        ffmpeg::ensure_features_supported(&app_handle, &["xfade"])?;
+    }
+
+    let (job, output_path) = JobContext::register_output(
+        &app_handle,
+        &image_paths,
+        &output_path,
+        CollisionSettings::load(&app_handle).with_policy(options.collision_policy),
+    )?;
+    let conversion_id = job.id.clone();
+
+    info!(
+        "Starting slideshow of {} images -> {} (Process ID: {})",
+        image_paths.len(),
+        output_path,
+        job.short_id()
+    );
+
+    job::spawn_job(job, move |job| async move {
+        perform_slideshow(&image_paths, &output_path, &options, &job).await
+    });
+
+    Ok(conversion_id)
+}
+
+async fn perform_timelapse(
+    input_path: &str,
+    output_path: &str,
+    options: &TimelapseOptions,
+    job: &JobContext,
+) -> Result<String> {
+    let info = metadata::probe_media_info(input_path)?;
+    let fps = options.fps.unwrap_or(DEFAULT_FPS).max(1);
+
+    // Each sampled frame becomes one output frame.
+    let output_duration = info
+        .duration
+        .map(|d| (d / options.interval_seconds) / fps as f64);
+
+    let mut cmd = Command::new(path::ffmpeg_path());
+    cmd.args(["-y", "-i", input_path]);
+    cmd.args([
+        "-vf",
+        &format!(
+            "select='isnan(prev_selected_t)+gte(t-prev_selected_t\\,{})',setpts=N/({}*TB)",
+            options.interval_seconds, fps
+        ),
+    ]);
+    cmd.args(["-r", &fps.to_string(), "-an"]);
+    output_format_config(output_path, &options.quality)?.apply_to_command(&mut cmd);
+    cmd.args(["-pix_fmt", "yuv420p"]);
+    let output = AtomicOutput::new(output_path, job.short_id())?;
+    cmd.arg(output.temp_path());
+
+    job.run_ffmpeg(&mut cmd, output_duration, (0.0, 100.0))?;
+    output.commit()?;
+
+    Ok(output_path.to_string())
+}
+
+async fn perform_slideshow(
+    image_paths: &[String],
+    output_path: &str,
+    options: &SlideshowOptions,
+    job: &JobContext,
+) -> Result<String> {
+    let fps = options.fps.unwrap_or(DEFAULT_FPS).max(1);
+    let width = options.width.unwrap_or(DEFAULT_SLIDESHOW_SIZE.0) / 2 * 2;
+    let height = options.height.unwrap_or(DEFAULT_SLIDESHOW_SIZE.1) / 2 * 2;
+    let count = image_paths.len();
+    let transition = options.transition_duration;
+    let total_duration = count as f64 * options.image_duration - (count - 1) as f64 * transition;
+
+    let mut cmd = Command::new(path::ffmpeg_path());
+    cmd.arg("-y");
+    for image_path in image_paths {
+        cmd.args([
+            "-loop",
+            "1",
+            "-t",
+            &format!("{:.3}", options.image_duration),
+        ]);
+        cmd.args(["-i", image_path]);
+    }
+    if let Some(audio_path) = &options.audio_path {
+        cmd.args(["-stream_loop", "-1", "-i", audio_path]);
+    }
+
+    let mut graph = String::new();
+    for i in 0..count {
+        graph.push_str(&format!(
+            "[{i}:v]scale={width}:{height}:force_original_aspect_ratio=decrease,\
+             pad={width}:{height}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={fps},format=yuv420p[v{i}];"
+        ));
+    }
+    graph.push_str(&build_transition_graph(
+        count,
+        options.image_duration,
+        transition,
+    ));
+
+    cmd.args(["-filter_complex", &graph, "-map", "[vout]"]);
+    if options.audio_path.is_some() {
+        cmd.args(["-map", &format!("{}:a:0", count)]);
+        let fade_start = (total_duration - 1.0).max(0.0);
+        cmd.args(["-af", &format!("afade=t=out:st={:.3}:d=1", fade_start)]);
+    }
+    cmd.args(["-t", &format!("{:.3}", total_duration)]);
+    output_format_config(output_path, &options.quality)?.apply_to_command(&mut cmd);
+    let output = AtomicOutput::new(output_path, job.short_id())?;
+    cmd.arg(output.temp_path());
+
+    job.run_ffmpeg(&mut cmd, Some(total_duration), (0.0, 100.0))?;
+    output.commit()?;
+
+    Ok(output_path.to_string())
+}
+
+/// Joins the normalised `[v0]`..`[vN]` streams into `[vout]`, crossfading
+/// between them when a transition length is set.
+fn build_transition_graph(count: usize, image_duration: f64, transition: f64) -> String {
+    if count == 1 {
+        return "[v0]null[vout]".to_string();
+    }
+
+    if transition <= 0.0 {
+        let inputs: String = (0..count).map(|i| format!("[v{}]", i)).collect();
+        return format!("{}concat=n={}:v=1:a=0[vout]", inputs, count);
+    }
+
+    let mut graph = String::new();
+    let mut previous = "v0".to_string();
+    for i in 1..count {
+        let label = if i == count - 1 {
+            "vout".to_string()
+        } else {
+            format!("x{}", i)
+        };
+        // Each crossfade starts `transition` seconds before the current image ends.
+        let offset = i as f64 * (image_duration - transition);
+        graph.push_str(&format!(
+            "[{}][v{}]xfade=transition=fade:duration={:.3}:offset={:.3}[{}]",
+            previous, i, transition, offset, label
+        ));
+        if i != count - 1 {
+            graph.push(';');
+        }
+        previous = label;
+    }
+
+    graph
+}
+
+/// Validates slideshow inputs before a job is started.
+fn validate_slideshow(image_paths: &[String], options: &SlideshowOptions) -> Result<()> {
+    if image_paths.is_empty() {
+        return Err(
+            FileDittoError::InvalidOptions("A slideshow needs at least one image".into()).into(),
+        );
+    }
+    if options.image_duration <= 0.0 {
+        return Err(FileDittoError::InvalidOptions(
+            format!(
+                "Image duration must be positive: {}",
+                options.image_duration
+            )
+            .into(),
+        )
+        .into());
+    }
+    if options.transition_duration < 0.0 || options.transition_duration >= options.image_duration {
+        return Err(FileDittoError::InvalidOptions(format!("Transition length must be at least 0 and shorter than the image duration ({}s): {}",
+            options.image_duration,
+            options.transition_duration).into()).into());
+    }
+    for image_path in image_paths {
+        if !Path::new(image_path).exists() {
+            return Err(FileDittoError::InputMissing(
+                format!("Image does not exist: {}", image_path).into(),
+            )
+            .into());
+        }
+    }
+    if let Some(audio_path) = &options.audio_path {
+        if !Path::new(audio_path).exists() {
+            return Err(FileDittoError::InputMissing(
+                format!("Audio file does not exist: {}", audio_path).into(),
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Looks up the encoder settings for the output path's container.
+fn output_format_config(
+    output_path: &str,
+    quality: &str,
+) -> Result<conversion_settings::FormatConfig> {
+    let output_format = Path::new(output_path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    conversion_settings::get_format_config(&output_format, quality)
+}
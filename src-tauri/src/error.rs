@@ -0,0 +1,189 @@
+//! Structured errors returned by every command.
+//!
+//! Errors serialize as `{ "code": "disk_full", "message": "...", "log_tail": "..." }`
+//! so the UI can offer a recovery action per code. Internal helpers keep using
+//! `anyhow`; a `FileDittoError` raised inside them survives the trip and is
+//! recovered by `From<anyhow::Error>` at the command boundary.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+
+/// Number of FFmpeg stderr lines kept with an error.
+const LOG_TAIL_LINES: usize = 20;
+
+/// Human message and optional FFmpeg output attached to every error.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorDetails {
+    pub message: String,
+    /// Last lines of FFmpeg's stderr, when the error came from an FFmpeg run.
+    pub log_tail: Option<String>,
+}
+
+impl From<String> for ErrorDetails {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            log_tail: None,
+        }
+    }
+}
+
+impl From<&str> for ErrorDetails {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
+}
+
+/// An error the UI can act on, tagged with a machine-readable `code`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum FileDittoError {
+    /// An input file or directory does not exist.
+    InputMissing(ErrorDetails),
+    PermissionDenied(ErrorDetails),
+    /// The FFmpeg build lacks a required encoder.
+    UnknownEncoder(ErrorDetails),
+    /// The container, codec or input format is not supported.
+    UnsupportedFormat(ErrorDetails),
+    DiskFull(ErrorDetails),
+    /// The output path is taken and the collision policy is `ask`.
+    OutputExists(ErrorDetails),
+    /// The output path is taken and the collision policy is `skip`.
+    Skipped(ErrorDetails),
+    Cancelled(ErrorDetails),
+    /// The FFmpeg or FFprobe executable could not be started.
+    FfmpegNotFound(ErrorDetails),
+    /// The FFmpeg build is older than required or lacks a filter, encoder or
+    /// muxer that a feature needs.
+    FfmpegTooOld(ErrorDetails),
+    /// FFprobe could not read the input.
+    ProbeFailed(ErrorDetails),
+    InvalidSettings(ErrorDetails),
+    /// Command arguments or options are out of range or inconsistent.
+    InvalidOptions(ErrorDetails),
+    /// FFmpeg failed for a reason not covered by a more specific code.
+    FfmpegFailed(ErrorDetails),
+    Io(ErrorDetails),
+    Internal(ErrorDetails),
+}
+
+impl FileDittoError {
+    pub fn details(&self) -> &ErrorDetails {
+        match self {
+            Self::InputMissing(details)
+            | Self::PermissionDenied(details)
+            | Self::UnknownEncoder(details)
+            | Self::UnsupportedFormat(details)
+            | Self::DiskFull(details)
+            | Self::OutputExists(details)
+            | Self::Skipped(details)
+            | Self::Cancelled(details)
+            | Self::FfmpegNotFound(details)
+            | Self::FfmpegTooOld(details)
+            | Self::ProbeFailed(details)
+            | Self::InvalidSettings(details)
+            | Self::InvalidOptions(details)
+            | Self::FfmpegFailed(details)
+            | Self::Io(details)
+            | Self::Internal(details) => details,
+        }
+    }
+
+    fn details_mut(&mut self) -> &mut ErrorDetails {
+        match self {
+            Self::InputMissing(details)
+            | Self::PermissionDenied(details)
+            | Self::UnknownEncoder(details)
+            | Self::UnsupportedFormat(details)
+            | Self::DiskFull(details)
+            | Self::OutputExists(details)
+            | Self::Skipped(details)
+            | Self::Cancelled(details)
+            | Self::FfmpegNotFound(details)
+            | Self::FfmpegTooOld(details)
+            | Self::ProbeFailed(details)
+            | Self::InvalidSettings(details)
+            | Self::InvalidOptions(details)
+            | Self::FfmpegFailed(details)
+            | Self::Io(details)
+            | Self::Internal(details) => details,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.details().message
+    }
+
+    /// Attaches the last lines of FFmpeg's stderr.
+    pub fn with_log_tail(mut self, stderr_output: &str) -> Self {
+        self.details_mut().log_tail = Some(log_tail(stderr_output));
+        self
+    }
+
+    /// Classifies a failed FFmpeg run from its stderr.
+    pub fn from_ffmpeg_stderr(stderr_output: &str) -> Self {
+        let error = if stderr_output.contains("No space left on device") {
+            Self::DiskFull("Not enough disk space to write the output".into())
+        } else if stderr_output.contains("Permission denied") {
+            Self::PermissionDenied("Permission denied - check file/directory permissions".into())
+        } else if stderr_output.contains("No such file or directory") {
+            Self::InputMissing("Input file not found or inaccessible".into())
+        } else if stderr_output.contains("Unknown encoder")
+            || stderr_output.contains("Encoder not found")
+        {
+            Self::UnknownEncoder("Unsupported encoder for this format".into())
+        } else if stderr_output.contains("Invalid data found when processing input")
+            || stderr_output.contains("Unable to find a suitable output format")
+            || stderr_output.contains("Could not find tag for codec")
+            || stderr_output.contains("not currently supported in container")
+        {
+            Self::UnsupportedFormat("Input or output format is not supported".into())
+        } else if stderr_output.contains("Invalid argument") {
+            Self::FfmpegFailed("Invalid FFmpeg arguments or unsupported codec".into())
+        } else {
+            Self::FfmpegFailed("General FFmpeg error".into())
+        };
+        error.with_log_tail(stderr_output)
+    }
+
+    /// Classifies an I/O error, using `context` to describe what was attempted.
+    pub fn from_io(error: &io::Error, context: &str) -> Self {
+        let message = format!("{}: {}", context, error);
+        match error.kind() {
+            io::ErrorKind::NotFound => Self::InputMissing(message.into()),
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied(message.into()),
+            io::ErrorKind::StorageFull => Self::DiskFull(message.into()),
+            _ => Self::Io(message.into()),
+        }
+    }
+}
+
+impl fmt::Display for FileDittoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for FileDittoError {}
+
+impl From<anyhow::Error> for FileDittoError {
+    fn from(error: anyhow::Error) -> Self {
+        if let Some(error) = error.downcast_ref::<FileDittoError>() {
+            return error.clone();
+        }
+        if let Some(io_error) = error.downcast_ref::<io::Error>() {
+            return Self::from_io(io_error, "I/O error");
+        }
+        Self::Internal(error.to_string().into())
+    }
+}
+
+/// Last `LOG_TAIL_LINES` non-empty lines of FFmpeg's stderr.
+fn log_tail(stderr_output: &str) -> String {
+    let lines: Vec<&str> = stderr_output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    lines[lines.len().saturating_sub(LOG_TAIL_LINES)..].join("\n")
+}
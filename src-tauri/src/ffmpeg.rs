@@ -1,16 +1,286 @@
 //! FFmpeg availability checking and utilities.
 
+use crate::conversion_settings;
+use crate::error::FileDittoError;
 use crate::path;
+use crate::types::CapabilitiesCache;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
 use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::{AppHandle, Manager};
+use tracing::{debug, error, warn};
+
+/// A video encoder that can be chosen for an output format.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoCodecOption {
+    pub codec: String,
+    /// False when the capability probe has not finished yet or failed.
+    pub verified: bool,
+}
+
+/// Oldest FFmpeg release the app supports. The job runner parses `out_time_us`
+/// from `-progress` output, which first appeared in 4.1.
+pub const MINIMUM_VERSION: FfmpegVersion = FfmpegVersion::new(4, 1, 0);
+
+/// A numbered FFmpeg release.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct FfmpegVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl FfmpegVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parses release strings such as `6.1.1`, `n5.1.2` or `4.4.2-0ubuntu0.22.04.1`.
+    ///
+    /// Returns `None` for git snapshots (`N-112345-g…`, `2024-01-01-git-…`),
+    /// which carry no release number.
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.strip_prefix('n').unwrap_or(version);
+        let numeric: String = version
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == '.')
+            .collect();
+        let mut parts = numeric.split('.').map(|part| part.parse::<u32>().ok());
+
+        let major = parts.next()??;
+        // Date-stamped snapshot builds start with a four-digit year
+        if major >= 1000 {
+            return None;
+        }
+        let minor = parts.next().flatten().unwrap_or(0);
+        let patch = parts.next().flatten().unwrap_or(0);
+        Some(Self::new(major, minor, patch))
+    }
+}
+
+impl fmt::Display for FfmpegVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.patch == 0 {
+            write!(f, "{}.{}", self.major, self.minor)
+        } else {
+            write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+        }
+    }
+}
+
+/// An optional FFmpeg feature the app uses, and what the build needs for it.
+#[derive(Debug, Clone, Copy)]
+pub struct FeatureRequirement {
+    /// Filter, encoder or muxer name, also used as the feature ID.
+    pub id: &'static str,
+    pub description: &'static str,
+    pub min_version: Option<FfmpegVersion>,
+    pub kind: FeatureKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureKind {
+    Filter,
+    Encoder,
+    Muxer,
+}
+
+/// Number of the most recently started capability probe.
+static PROBE_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+const FEATURE_REQUIREMENTS: &[FeatureRequirement] = &[
+    FeatureRequirement {
+        id: "minterpolate",
+        description: "Motion-interpolated frame rate conversion",
+        min_version: Some(FfmpegVersion::new(3, 2, 0)),
+        kind: FeatureKind::Filter,
+    },
+    FeatureRequirement {
+        id: "xfade",
+        description: "Slideshow crossfades",
+        min_version: Some(FfmpegVersion::new(4, 3, 0)),
+        kind: FeatureKind::Filter,
+    },
+    FeatureRequirement {
+        id: "palettegen",
+        description: "GIF palette generation",
+        min_version: None,
+        kind: FeatureKind::Filter,
+    },
+    FeatureRequirement {
+        id: "paletteuse",
+        description: "GIF palette mapping",
+        min_version: None,
+        kind: FeatureKind::Filter,
+    },
+    FeatureRequirement {
+        id: "select",
+        description: "Timelapse frame sampling",
+        min_version: None,
+        kind: FeatureKind::Filter,
+    },
+    FeatureRequirement {
+        id: "drawtext",
+        description: "Text overlays",
+        min_version: None,
+        kind: FeatureKind::Filter,
+    },
+    FeatureRequirement {
+        id: "libsvtav1",
+        description: "AV1 encoding with SVT-AV1",
+        min_version: Some(FfmpegVersion::new(4, 4, 0)),
+        kind: FeatureKind::Encoder,
+    },
+    FeatureRequirement {
+        id: "libwebp",
+        description: "Animated WebP export",
+        min_version: None,
+        kind: FeatureKind::Encoder,
+    },
+    FeatureRequirement {
+        id: "libx264",
+        description: "H.264 streaming renditions",
+        min_version: None,
+        kind: FeatureKind::Encoder,
+    },
+    FeatureRequirement {
+        id: "hls",
+        description: "HLS packaging",
+        min_version: None,
+        kind: FeatureKind::Muxer,
+    },
+    FeatureRequirement {
+        id: "dash",
+        description: "DASH packaging",
+        min_version: None,
+        kind: FeatureKind::Muxer,
+    },
+    FeatureRequirement {
+        id: "segment",
+        description: "Splitting by duration or size",
+        min_version: None,
+        kind: FeatureKind::Muxer,
+    },
+];
+
+/// A feature the FFmpeg build cannot provide.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompatibilityWarning {
+    /// Feature ID, or `ffmpeg` for the minimum version check.
+    pub feature: String,
+    pub message: String,
+    pub required_version: Option<String>,
+}
+
+/// What the installed FFmpeg build can do, parsed from its listing flags.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FfmpegCapabilities {
+    /// Version string as reported by `ffmpeg -version`, e.g. `6.1.1` or `N-112345-gabcdef`.
+    pub version: String,
+    /// Release number, or `None` for git snapshot builds.
+    pub parsed_version: Option<FfmpegVersion>,
+    /// Arguments FFmpeg was configured with, e.g. `--enable-libx264`.
+    pub configuration: Vec<String>,
+    /// External libraries enabled at build time, e.g. `libx264`.
+    pub enabled_libraries: Vec<String>,
+    pub encoders: BTreeSet<String>,
+    pub decoders: BTreeSet<String>,
+    pub muxers: BTreeSet<String>,
+    pub demuxers: BTreeSet<String>,
+    pub filters: BTreeSet<String>,
+    pub hwaccels: Vec<String>,
+    /// Features that will be unavailable with this build.
+    pub warnings: Vec<CompatibilityWarning>,
+}
+
+impl FfmpegCapabilities {
+    pub fn has_encoder(&self, name: &str) -> bool {
+        self.encoders.contains(name)
+    }
+
+    pub fn has_filter(&self, name: &str) -> bool {
+        self.filters.contains(name)
+    }
+
+    pub fn has_muxer(&self, name: &str) -> bool {
+        self.muxers.contains(name)
+    }
+
+    /// Checks the build against the minimum version and every known feature.
+    fn compatibility_warnings(&self) -> Vec<CompatibilityWarning> {
+        let mut warnings: Vec<CompatibilityWarning> =
+            self.check_minimum_version().into_iter().collect();
+        warnings.extend(
+            FEATURE_REQUIREMENTS
+                .iter()
+                .filter_map(|requirement| self.check_feature(requirement)),
+        );
+        warnings
+    }
+
+    fn check_minimum_version(&self) -> Option<CompatibilityWarning> {
+        match self.parsed_version {
+            Some(version) if version < MINIMUM_VERSION => Some(CompatibilityWarning {
+                feature: "ffmpeg".to_string(),
+                message: format!(
+                    "FFmpeg {} is too old; version {} or newer is required",
+                    version, MINIMUM_VERSION
+                ),
+                required_version: Some(MINIMUM_VERSION.to_string()),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns a warning if the build cannot provide a feature.
+    ///
+    /// Snapshot builds without a release number are assumed to be recent
+    /// enough, so only the presence of the filter, encoder or muxer is checked.
+    fn check_feature(&self, requirement: &FeatureRequirement) -> Option<CompatibilityWarning> {
+        if let (Some(version), Some(required)) = (self.parsed_version, requirement.min_version) {
+            if version < required {
+                return Some(CompatibilityWarning {
+                    feature: requirement.id.to_string(),
+                    message: format!(
+                        "{} ({}) requires FFmpeg ≥ {}, found {}",
+                        requirement.description, requirement.id, required, version
+                    ),
+                    required_version: Some(required.to_string()),
+                });
+            }
+        }
+
+        let present = match requirement.kind {
+            FeatureKind::Filter => self.has_filter(requirement.id),
+            FeatureKind::Encoder => self.has_encoder(requirement.id),
+            FeatureKind::Muxer => self.has_muxer(requirement.id),
+        };
+        (!present).then(|| CompatibilityWarning {
+            feature: requirement.id.to_string(),
+            message: format!(
+                "{} ({}) is not included in this FFmpeg build",
+                requirement.description, requirement.id
+            ),
+            required_version: None,
+        })
+    }
+}
 
 /// Checks if FFmpeg and FFprobe are available and working.
 #[tauri::command]
-pub async fn check_ffmpeg_availability() -> Result<bool, String> {
+pub async fn check_ffmpeg_availability() -> Result<bool, FileDittoError> {
     let ffmpeg_path = path::ffmpeg_path();
     let ffprobe_path = path::ffprobe_path();
 
-    let ffmpeg_check = Command::new(&ffmpeg_path).args(&["-version"]).output();
-    let ffprobe_check = Command::new(&ffprobe_path).args(&["-version"]).output();
+    let ffmpeg_check = Command::new(&ffmpeg_path).args(["-version"]).output();
+    let ffprobe_check = Command::new(&ffprobe_path).args(["-version"]).output();
 
     match (ffmpeg_check, ffprobe_check) {
         (Ok(ffmpeg_output), Ok(ffprobe_output)) => {
@@ -18,14 +288,301 @@ pub async fn check_ffmpeg_availability() -> Result<bool, String> {
             let ffprobe_success = ffprobe_output.status.success();
 
             if !(ffmpeg_success && ffprobe_success) {
-                println!("❌ FFmpeg availability check failed");
+                error!("FFmpeg availability check failed");
             }
 
             Ok(ffmpeg_success && ffprobe_success)
         }
         _ => {
-            println!("❌ Failed to execute FFmpeg commands");
+            error!("Failed to execute FFmpeg commands");
             Ok(false)
         }
     }
 }
+
+/// Probes the FFmpeg build for its version, codecs, formats, filters and
+/// hardware acceleration methods.
+pub fn probe_capabilities() -> Result<FfmpegCapabilities> {
+    let version_output = run_listing("-version")?;
+    let (version, configuration) = parse_version_output(&version_output);
+    let enabled_libraries = configuration
+        .iter()
+        .filter_map(|arg| arg.strip_prefix("--enable-"))
+        .filter(|name| name.starts_with("lib"))
+        .map(String::from)
+        .collect();
+
+    let mut capabilities = FfmpegCapabilities {
+        parsed_version: FfmpegVersion::parse(&version),
+        version,
+        configuration,
+        enabled_libraries,
+        encoders: parse_codec_listing(&run_listing("-encoders")?),
+        decoders: parse_codec_listing(&run_listing("-decoders")?),
+        muxers: parse_format_listing(&run_listing("-muxers")?),
+        demuxers: parse_format_listing(&run_listing("-demuxers")?),
+        filters: parse_filter_listing(&run_listing("-filters")?),
+        hwaccels: parse_hwaccel_listing(&run_listing("-hwaccels")?),
+        warnings: Vec::new(),
+    };
+    capabilities.warnings = capabilities.compatibility_warnings();
+    Ok(capabilities)
+}
+
+/// Runs `ffmpeg -hide_banner <flag>` and returns its standard output.
+fn run_listing(flag: &str) -> Result<String> {
+    let output = Command::new(path::ffmpeg_path())
+        .args(["-hide_banner", flag])
+        .output()
+        .map_err(|e| {
+            FileDittoError::FfmpegNotFound(format!("Failed to execute FFmpeg: {}", e).into())
+        })?;
+
+    if !output.status.success() {
+        return Err(anyhow!("FFmpeg {} listing failed", flag));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Extracts the version and configure arguments from `ffmpeg -version`.
+fn parse_version_output(output: &str) -> (String, Vec<String>) {
+    let version = output
+        .lines()
+        .find_map(|line| line.strip_prefix("ffmpeg version "))
+        .and_then(|rest| rest.split_whitespace().next())
+        .unwrap_or("unknown")
+        .to_string();
+
+    let configuration = output
+        .lines()
+        .find_map(|line| line.strip_prefix("configuration:"))
+        .map(|args| args.split_whitespace().map(String::from).collect())
+        .unwrap_or_default();
+
+    (version, configuration)
+}
+
+/// Parses the ` V....D libx264  description` rows of an encoder or decoder listing.
+fn parse_codec_listing(listing: &str) -> BTreeSet<String> {
+    listing
+        .lines()
+        // Rows start after the " ------" separator line
+        .skip_while(|line| !line.trim_start().starts_with("---"))
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(String::from)
+        .collect()
+}
+
+/// Parses the `  E mp4  MP4 (MPEG-4 Part 14)` rows of a muxer or demuxer listing.
+///
+/// Demuxers may cover several names, e.g. `mov,mp4,m4a,3gp,3g2,mj2`.
+fn parse_format_listing(listing: &str) -> BTreeSet<String> {
+    listing
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("--"))
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .flat_map(|names| names.split(','))
+        .map(String::from)
+        .collect()
+}
+
+/// Parses the ` TSC loudnorm  A->A  description` rows of a filter listing.
+fn parse_filter_listing(listing: &str) -> BTreeSet<String> {
+    listing
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _flags = fields.next()?;
+            let name = fields.next()?;
+            let io = fields.next()?;
+            io.contains("->").then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// Parses the method names listed under `Hardware acceleration methods:`.
+fn parse_hwaccel_listing(listing: &str) -> Vec<String> {
+    listing
+        .lines()
+        .skip_while(|line| !line.starts_with("Hardware acceleration methods"))
+        .skip(1)
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Probes the FFmpeg build in the background and caches the report in app state.
+///
+/// A probe started later, for example after the FFmpeg path changed, takes
+/// precedence even if this one finishes after it.
+pub fn spawn_capability_probe(app_handle: AppHandle) {
+    let generation = next_probe_generation();
+    std::thread::spawn(move || match probe_capabilities() {
+        Ok(capabilities) => {
+            debug!(
+                "Detected FFmpeg {} ({} encoders, {} filters)",
+                capabilities.version,
+                capabilities.encoders.len(),
+                capabilities.filters.len()
+            );
+            for warning in &capabilities.warnings {
+                warn!("{}", warning.message);
+            }
+            store_capabilities(&app_handle, generation, capabilities);
+        }
+        Err(e) => warn!("Could not probe FFmpeg capabilities: {}", e),
+    });
+}
+
+/// Numbers a new capability probe, superseding every earlier one.
+fn next_probe_generation() -> u64 {
+    PROBE_GENERATION.fetch_add(1, Ordering::SeqCst) + 1
+}
+
+/// Caches a probe's report unless a newer probe has started since.
+fn store_capabilities(app_handle: &AppHandle, generation: u64, capabilities: FfmpegCapabilities) {
+    let cache = app_handle.state::<CapabilitiesCache>();
+    let mut cached = cache.lock().unwrap();
+    // Checked under the lock so a newer probe cannot store in between
+    if PROBE_GENERATION.load(Ordering::SeqCst) != generation {
+        debug!(
+            "Discarding capabilities from superseded probe {}",
+            generation
+        );
+        return;
+    }
+    *cached = Some(capabilities);
+}
+
+/// Returns the cached capability report, if the probe has finished.
+pub fn cached_capabilities(app_handle: &AppHandle) -> Option<FfmpegCapabilities> {
+    let cache = app_handle.state::<CapabilitiesCache>();
+    let capabilities = cache.lock().unwrap();
+    capabilities.clone()
+}
+
+/// Returns a structured report of the FFmpeg build's capabilities.
+///
+/// The report is cached after the first probe; pass `refresh` to probe again,
+/// for example after the user installs a different FFmpeg build.
+#[tauri::command]
+pub async fn get_ffmpeg_capabilities(
+    refresh: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<FfmpegCapabilities, FileDittoError> {
+    let cache = app_handle.state::<CapabilitiesCache>();
+    if !refresh.unwrap_or(false) {
+        if let Some(capabilities) = cache.lock().unwrap().as_ref() {
+            return Ok(capabilities.clone());
+        }
+    }
+
+    let generation = next_probe_generation();
+    let capabilities = probe_capabilities()?;
+    store_capabilities(&app_handle, generation, capabilities.clone());
+    Ok(capabilities)
+}
+
+/// Fails with a clear message if the FFmpeg build lacks an encoder.
+///
+/// Passes when the capability probe has not completed, leaving FFmpeg itself
+/// to report the problem.
+pub fn ensure_encoder_available(app_handle: &AppHandle, encoder: &str) -> Result<()> {
+    match cached_capabilities(app_handle) {
+        Some(capabilities) if !capabilities.has_encoder(encoder) => Err(FileDittoError::UnknownEncoder(format!(
+            "The '{}' encoder is not available in this FFmpeg build. Choose a different codec or install an FFmpeg build that includes it.",
+            encoder
+        ).into()).into()),
+        _ => Ok(()),
+    }
+}
+
+/// Fails with the specific reasons if the FFmpeg build is older than the
+/// supported minimum or cannot provide one of `features`.
+///
+/// Passes when the capability probe has not completed.
+pub fn ensure_features_supported(app_handle: &AppHandle, features: &[&str]) -> Result<()> {
+    let Some(capabilities) = cached_capabilities(app_handle) else {
+        return Ok(());
+    };
+
+    let problems: Vec<String> = capabilities
+        .check_minimum_version()
+        .into_iter()
+        .chain(
+            FEATURE_REQUIREMENTS
+                .iter()
+                .filter(|requirement| features.contains(&requirement.id))
+                .filter_map(|requirement| capabilities.check_feature(requirement)),
+        )
+        .map(|warning| warning.message)
+        .collect();
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(FileDittoError::FfmpegTooOld(problems.join("; ").into()).into())
+    }
+}
+
+/// Lists the video encoders available for an output format.
+///
+/// Encoders missing from the FFmpeg build are left out once the startup
+/// probe has finished.
+#[tauri::command]
+pub async fn list_video_codecs(
+    output_format: String,
+    app_handle: AppHandle,
+) -> Vec<VideoCodecOption> {
+    let capabilities = cached_capabilities(&app_handle);
+
+    conversion_settings::supported_video_codecs(&output_format)
+        .iter()
+        .filter(|codec| {
+            capabilities
+                .as_ref()
+                .map(|c| c.has_encoder(codec))
+                .unwrap_or(true)
+        })
+        .map(|codec| VideoCodecOption {
+            codec: codec.to_string(),
+            verified: capabilities.is_some(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_release_versions() {
+        assert_eq!(
+            FfmpegVersion::parse("6.1.1"),
+            Some(FfmpegVersion::new(6, 1, 1))
+        );
+        assert_eq!(
+            FfmpegVersion::parse("n5.1.2"),
+            Some(FfmpegVersion::new(5, 1, 2))
+        );
+        assert_eq!(
+            FfmpegVersion::parse("4.4.2-0ubuntu0.22.04.1"),
+            Some(FfmpegVersion::new(4, 4, 2))
+        );
+        assert_eq!(
+            FfmpegVersion::parse("7.0"),
+            Some(FfmpegVersion::new(7, 0, 0))
+        );
+    }
+
+    #[test]
+    fn snapshot_builds_have_no_version() {
+        assert_eq!(FfmpegVersion::parse("N-112345-g1a2b3c4d5e"), None);
+        assert_eq!(FfmpegVersion::parse("2024-01-01-git-1a2b3c4d5e"), None);
+        assert_eq!(FfmpegVersion::parse(""), None);
+    }
+}
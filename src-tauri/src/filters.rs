@@ -0,0 +1,555 @@
+//! FFmpeg filter graph construction for per-conversion video adjustments.
+
+use crate::error::FileDittoError;
+use crate::metadata;
+use crate::presets;
+use crate::types::ConversionOptions;
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Slowest supported playback speed factor.
+const MIN_SPEED: f64 = 0.0625;
+
+/// Fastest supported playback speed factor.
+const MAX_SPEED: f64 = 16.0;
+
+/// Highest supported output frame rate.
+const MAX_FPS: f64 = 240.0;
+
+/// Colour names and `#RRGGBB`/`0xRRGGBB` values, compiled on first use.
+static COLOR_PATTERN: OnceLock<Regex> = OnceLock::new();
+
+/// Corner or centre an overlay is anchored to.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+/// What to draw on top of the video.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OverlayContent {
+    /// An image file, typically a PNG logo with transparency.
+    Image { path: String },
+    /// A line of text.
+    Text {
+        text: String,
+        /// Font family name, or a path to a font file.
+        font: Option<String>,
+        font_size: Option<u32>,
+        /// Colour name or `#RRGGBB`.
+        color: Option<String>,
+    },
+}
+
+/// Watermark or overlay settings for a conversion.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OverlayOptions {
+    pub content: OverlayContent,
+    #[serde(default)]
+    pub position: OverlayPosition,
+    /// Distance in pixels from the anchored edges.
+    #[serde(default)]
+    pub offset_x: u32,
+    #[serde(default)]
+    pub offset_y: u32,
+    /// 0.0 (invisible) to 1.0 (opaque). Defaults to 1.0.
+    pub opacity: Option<f32>,
+    /// Only show the overlay from this time, in seconds.
+    pub start_time: Option<f64>,
+    /// Only show the overlay until this time, in seconds.
+    pub end_time: Option<f64>,
+}
+
+/// Rotation and flip settings for a conversion.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OrientationOptions {
+    /// Physically rotate the pixels according to the source's rotation tag and
+    /// clear the tag, so players that ignore it still show the video upright.
+    #[serde(default)]
+    pub auto_rotate: bool,
+    /// Extra clockwise rotation in degrees: 90, 180 or 270.
+    pub rotate: Option<u32>,
+    #[serde(default)]
+    pub flip_horizontal: bool,
+    #[serde(default)]
+    pub flip_vertical: bool,
+}
+
+/// Output frame rate settings for a conversion.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameRateOptions {
+    pub fps: f64,
+    /// Synthesise in-between frames with motion interpolation instead of
+    /// dropping or duplicating frames. Much slower.
+    #[serde(default)]
+    pub interpolate: bool,
+}
+
+/// Filters and extra inputs derived from a set of conversion options.
+#[derive(Debug, Clone, Default)]
+pub struct FilterPlan {
+    /// Options that must precede the main `-i`.
+    input_options: Vec<String>,
+    /// Inputs added after the main input, referenced as `[1:v]`, `[2:v]`...
+    extra_inputs: Vec<String>,
+    /// Single-input filters applied to the main video stream, in order.
+    video_chain: Vec<String>,
+    /// Image overlay graph fragment taking `[base]` and `[1:v]`.
+    image_overlay: Option<String>,
+    /// Filters applied to the main audio stream, in order.
+    audio_chain: Vec<String>,
+    /// Options for the output file, added with the filters.
+    output_options: Vec<String>,
+    /// Playback speed factor; output duration is input duration divided by this.
+    speed: Option<f64>,
+}
+
+/// FFmpeg features (filter and encoder names) a conversion's options rely on,
+/// for checking against the installed build before the job starts.
+pub fn required_features(options: &ConversionOptions) -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if let Some(overlay) = &options.overlay {
+        if matches!(overlay.content, OverlayContent::Text { .. }) {
+            features.push("drawtext");
+        }
+    }
+    if options.frame_rate.as_ref().is_some_and(|f| f.interpolate) {
+        features.push("minterpolate");
+    }
+    if options.video_codec.as_deref() == Some("libsvtav1") {
+        features.push("libsvtav1");
+    }
+    features
+}
+
+impl FilterPlan {
+    /// Builds the filter plan for a conversion, validating any referenced files.
+    pub fn from_options(options: &ConversionOptions) -> Result<Self> {
+        let mut plan = FilterPlan::default();
+
+        // Orientation goes first so overlays are drawn upright on the final frame.
+        if let Some(orientation) = &options.orientation {
+            plan.add_orientation(orientation)?;
+        }
+
+        if let Some(speed) = options.speed {
+            plan.add_speed(speed)?;
+        }
+
+        if let Some(frame_rate) = &options.frame_rate {
+            plan.add_frame_rate(frame_rate)?;
+        }
+
+        // Device presets cap the resolution of the adjusted frame, before any
+        // overlay is drawn at its final size.
+        if let Some(preset_id) = &options.preset {
+            plan.video_chain
+                .push(presets::get_preset(preset_id)?.scale_filter());
+        }
+
+        if let Some(overlay) = &options.overlay {
+            plan.add_overlay(overlay)?;
+        }
+
+        Ok(plan)
+    }
+
+    /// Returns the expected output duration for an input of `input_duration` seconds.
+    pub fn output_duration(&self, input_duration: Option<f64>) -> Option<f64> {
+        input_duration.map(|d| d / self.speed.unwrap_or(1.0))
+    }
+
+    /// Adds the options that apply to the main input. Call right before its `-i`.
+    pub fn apply_input_options(&self, cmd: &mut Command) {
+        cmd.args(&self.input_options);
+    }
+
+    /// Adds the extra inputs this plan needs. Call right after the main `-i`.
+    pub fn apply_inputs(&self, cmd: &mut Command) {
+        for input in &self.extra_inputs {
+            cmd.args(["-i", input]);
+        }
+    }
+
+    /// Adds the filter, stream mapping and output arguments for this plan.
+    pub fn apply_filters(&self, cmd: &mut Command) {
+        match &self.image_overlay {
+            Some(overlay) => {
+                let chain = if self.video_chain.is_empty() {
+                    "null".to_string()
+                } else {
+                    self.video_chain.join(",")
+                };
+                cmd.args([
+                    "-filter_complex",
+                    &format!("[0:v]{}[base];{}", chain, overlay),
+                ]);
+                cmd.args(["-map", "[vout]", "-map", "0:a?"]);
+            }
+            None if !self.video_chain.is_empty() => {
+                cmd.args(["-vf", &self.video_chain.join(",")]);
+            }
+            None => {}
+        }
+        if !self.audio_chain.is_empty() {
+            cmd.args(["-af", &self.audio_chain.join(",")]);
+        }
+        cmd.args(&self.output_options);
+    }
+
+    fn add_speed(&mut self, speed: f64) -> Result<()> {
+        if !(MIN_SPEED..=MAX_SPEED).contains(&speed) {
+            return Err(FileDittoError::InvalidOptions(
+                format!(
+                    "Playback speed must be between {}x and {}x: {}",
+                    MIN_SPEED, MAX_SPEED, speed
+                )
+                .into(),
+            )
+            .into());
+        }
+        if (speed - 1.0).abs() < f64::EPSILON {
+            return Ok(());
+        }
+
+        self.speed = Some(speed);
+        self.video_chain.push(format!("setpts=PTS/{}", speed));
+        self.audio_chain.extend(atempo_chain(speed));
+        Ok(())
+    }
+
+    fn add_frame_rate(&mut self, frame_rate: &FrameRateOptions) -> Result<()> {
+        if !(frame_rate.fps > 0.0 && frame_rate.fps <= MAX_FPS) {
+            return Err(FileDittoError::InvalidOptions(
+                format!(
+                    "Frame rate must be between 0 and {} fps: {}",
+                    MAX_FPS, frame_rate.fps
+                )
+                .into(),
+            )
+            .into());
+        }
+
+        if frame_rate.interpolate {
+            self.video_chain
+                .push(format!("minterpolate=fps={}:mi_mode=mci", frame_rate.fps));
+        } else {
+            self.video_chain.push(format!("fps={}", frame_rate.fps));
+        }
+        Ok(())
+    }
+
+    fn add_orientation(&mut self, orientation: &OrientationOptions) -> Result<()> {
+        if let Some(rotate) = orientation.rotate {
+            if !matches!(rotate, 0 | 90 | 180 | 270) {
+                return Err(FileDittoError::InvalidOptions(
+                    format!("Rotation must be 90, 180 or 270 degrees: {}", rotate).into(),
+                )
+                .into());
+            }
+        }
+
+        // FFmpeg autorotates by default: it turns the pixels upright and drops
+        // the display matrix side data. The legacy `rotate` tag is cleared
+        // explicitly, as metadata copied from the input could otherwise bring
+        // it back and players would rotate the upright pixels a second time.
+        // Without auto-rotation the pixels are left as stored and the rotation
+        // is copied through for players to apply.
+        if orientation.auto_rotate {
+            self.output_options
+                .extend(["-metadata:s:v:0".to_string(), "rotate=0".to_string()]);
+        } else {
+            self.input_options.push("-noautorotate".to_string());
+        }
+
+        match orientation.rotate.unwrap_or(0) {
+            90 => self.video_chain.push("transpose=clock".to_string()),
+            180 => self.video_chain.push("hflip,vflip".to_string()),
+            270 => self.video_chain.push("transpose=cclock".to_string()),
+            _ => {}
+        }
+        if orientation.flip_horizontal {
+            self.video_chain.push("hflip".to_string());
+        }
+        if orientation.flip_vertical {
+            self.video_chain.push("vflip".to_string());
+        }
+
+        Ok(())
+    }
+
+    fn add_overlay(&mut self, overlay: &OverlayOptions) -> Result<()> {
+        let opacity = overlay.opacity.unwrap_or(1.0);
+        if !(0.0..=1.0).contains(&opacity) {
+            return Err(FileDittoError::InvalidOptions(
+                format!("Overlay opacity must be between 0 and 1: {}", opacity).into(),
+            )
+            .into());
+        }
+        let enable = enable_expression(overlay.start_time, overlay.end_time)?;
+
+        match &overlay.content {
+            OverlayContent::Image { path } => {
+                validate_overlay_image(path)?;
+                let (x, y) =
+                    anchor_expressions(overlay, "main_w", "main_h", "overlay_w", "overlay_h");
+                self.extra_inputs.push(path.clone());
+                self.image_overlay = Some(format!(
+                    "[1:v]format=rgba,colorchannelmixer=aa={:.2}[logo];[base][logo]overlay=x={}:y={}{}[vout]",
+                    opacity, x, y, enable
+                ));
+            }
+            OverlayContent::Text {
+                text,
+                font,
+                font_size,
+                color,
+            } => {
+                if text.is_empty() {
+                    return Err(FileDittoError::InvalidOptions(
+                        "Overlay text cannot be empty".into(),
+                    )
+                    .into());
+                }
+                let color = color.as_deref().unwrap_or("white");
+                validate_color(color)?;
+                let (x, y) = anchor_expressions(overlay, "w", "h", "tw", "th");
+
+                let mut drawtext = format!(
+                    "drawtext=expansion=none:text={}:fontsize={}:fontcolor={}@{:.2}:x={}:y={}",
+                    escape_filter_value(text),
+                    font_size.unwrap_or(24),
+                    color,
+                    opacity,
+                    x,
+                    y
+                );
+                if let Some(font) = font {
+                    if Path::new(font).is_file() {
+                        drawtext.push_str(&format!(":fontfile={}", escape_filter_value(font)));
+                    } else {
+                        drawtext.push_str(&format!(":font={}", escape_filter_value(font)));
+                    }
+                }
+                drawtext.push_str(&enable);
+                self.video_chain.push(drawtext);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Splits a tempo change into `atempo` filters, each within the 0.5-2.0 range
+/// the filter accepts.
+fn atempo_chain(speed: f64) -> Vec<String> {
+    let mut filters = Vec::new();
+    let mut remaining = speed;
+
+    while remaining > 2.0 {
+        filters.push("atempo=2.0".to_string());
+        remaining /= 2.0;
+    }
+    while remaining < 0.5 {
+        filters.push("atempo=0.5".to_string());
+        remaining /= 0.5;
+    }
+    filters.push(format!("atempo={:.6}", remaining));
+
+    filters
+}
+
+/// Returns the x and y expressions placing an overlay at its anchored corner.
+fn anchor_expressions(
+    overlay: &OverlayOptions,
+    main_w: &str,
+    main_h: &str,
+    item_w: &str,
+    item_h: &str,
+) -> (String, String) {
+    let (ox, oy) = (overlay.offset_x, overlay.offset_y);
+    let left = ox.to_string();
+    let top = oy.to_string();
+    let right = format!("{}-{}-{}", main_w, item_w, ox);
+    let bottom = format!("{}-{}-{}", main_h, item_h, oy);
+
+    match overlay.position {
+        OverlayPosition::TopLeft => (left, top),
+        OverlayPosition::TopRight => (right, top),
+        OverlayPosition::BottomLeft => (left, bottom),
+        OverlayPosition::BottomRight => (right, bottom),
+        OverlayPosition::Center => (
+            format!("({}-{})/2", main_w, item_w),
+            format!("({}-{})/2", main_h, item_h),
+        ),
+    }
+}
+
+/// Builds the timeline `enable` option limiting a filter to a time range.
+fn enable_expression(start: Option<f64>, end: Option<f64>) -> Result<String> {
+    match (start, end) {
+        (None, None) => Ok(String::new()),
+        (Some(start), Some(end)) if start >= end => Err(FileDittoError::InvalidOptions(
+            format!(
+                "Overlay start ({:.2}s) must be before its end ({:.2}s)",
+                start, end
+            )
+            .into(),
+        )
+        .into()),
+        (Some(start), Some(end)) => Ok(format!(":enable='between(t,{:.3},{:.3})'", start, end)),
+        (Some(start), None) => Ok(format!(":enable='gte(t,{:.3})'", start)),
+        (None, Some(end)) => Ok(format!(":enable='lte(t,{:.3})'", end)),
+    }
+}
+
+/// Ensures an overlay image exists and FFmpeg can decode it as an image.
+fn validate_overlay_image(path: &str) -> Result<()> {
+    if !Path::new(path).is_file() {
+        return Err(FileDittoError::InputMissing(
+            format!("Overlay image does not exist: {}", path).into(),
+        )
+        .into());
+    }
+
+    let info = metadata::probe_media_info(path).map_err(|e| {
+        FileDittoError::UnsupportedFormat(
+            format!("Overlay image could not be read: {} ({})", path, e).into(),
+        )
+    })?;
+    if info.width.is_none() || info.height.is_none() {
+        return Err(FileDittoError::UnsupportedFormat(
+            format!("Overlay file is not a decodable image: {}", path).into(),
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Accepts colour names and `#RRGGBB`/`0xRRGGBB` values.
+fn validate_color(color: &str) -> Result<()> {
+    let valid =
+        COLOR_PATTERN.get_or_init(|| Regex::new(r"^([A-Za-z]+|(#|0x)[0-9A-Fa-f]{6})$").unwrap());
+    if !valid.is_match(color) {
+        return Err(FileDittoError::InvalidOptions(
+            format!("Invalid overlay colour: {}", color).into(),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Escapes a value for use as a filter option inside a filter graph.
+///
+/// FFmpeg parses filter graphs in two passes: first the option value (where
+/// `\`, `'` and `:` are special), then the graph itself (where `\`, `'`, `[`,
+/// `]`, `,` and `;` are special). Each pass needs its own escaping.
+pub fn escape_filter_value(value: &str) -> String {
+    let option_escaped: String = value
+        .chars()
+        .flat_map(|c| match c {
+            '\\' | '\'' | ':' => vec!['\\', c],
+            c => vec![c],
+        })
+        .collect();
+
+    option_escaped
+        .chars()
+        .flat_map(|c| match c {
+            '\\' | '\'' | '[' | ']' | ',' | ';' => vec!['\\', c],
+            c => vec![c],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn orientation_plan(orientation: OrientationOptions) -> FilterPlan {
+        let mut plan = FilterPlan::default();
+        plan.add_orientation(&orientation).unwrap();
+        plan
+    }
+
+    fn command_args(plan: &FilterPlan) -> Vec<String> {
+        let mut cmd = Command::new("ffmpeg");
+        plan.apply_input_options(&mut cmd);
+        plan.apply_filters(&mut cmd);
+        cmd.get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn auto_rotate_clears_the_rotation_tag() {
+        let plan = orientation_plan(OrientationOptions {
+            auto_rotate: true,
+            rotate: Some(90),
+            ..Default::default()
+        });
+
+        assert_eq!(plan.video_chain, ["transpose=clock"]);
+        assert_eq!(
+            command_args(&plan),
+            ["-vf", "transpose=clock", "-metadata:s:v:0", "rotate=0"]
+        );
+    }
+
+    #[test]
+    fn without_auto_rotate_the_rotation_is_kept() {
+        let plan = orientation_plan(OrientationOptions {
+            auto_rotate: false,
+            flip_horizontal: true,
+            ..Default::default()
+        });
+
+        assert_eq!(plan.video_chain, ["hflip"]);
+        assert_eq!(command_args(&plan), ["-noautorotate", "-vf", "hflip"]);
+    }
+
+    #[test]
+    fn atempo_stays_within_filter_range() {
+        assert_eq!(atempo_chain(1.5), ["atempo=1.500000"]);
+        assert_eq!(atempo_chain(3.0), ["atempo=2.0", "atempo=1.500000"]);
+        assert_eq!(
+            atempo_chain(8.0),
+            ["atempo=2.0", "atempo=2.0", "atempo=2.000000"]
+        );
+        assert_eq!(
+            atempo_chain(0.1),
+            ["atempo=0.5", "atempo=0.5", "atempo=0.5", "atempo=0.800000"]
+        );
+    }
+
+    #[test]
+    fn colors_are_names_or_hex_values() {
+        assert!(validate_color("white").is_ok());
+        assert!(validate_color("#FF00aa").is_ok());
+        assert!(validate_color("0x336699").is_ok());
+        assert!(validate_color("red@0.5").is_err());
+        assert!(validate_color("#FFF").is_err());
+    }
+
+    #[test]
+    fn invalid_rotation_is_rejected() {
+        let mut plan = FilterPlan::default();
+        let orientation = OrientationOptions {
+            rotate: Some(45),
+            ..Default::default()
+        };
+        assert!(plan.add_orientation(&orientation).is_err());
+    }
+}
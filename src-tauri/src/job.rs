@@ -0,0 +1,336 @@
+//! Shared lifecycle for long-running FFmpeg jobs: state tracking, progress events
+//! and process handles for cancellation.
+
+use crate::error::FileDittoError;
+use crate::logging;
+use crate::output;
+use crate::settings::CollisionSettings;
+use crate::types::{ConversionProgress, ConversionResult, ConversionState, ProcessHandles};
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{debug, error, info, info_span, warn, Instrument};
+use uuid::Uuid;
+
+/// Output paths of every active job, which no other output may claim.
+pub fn reserved_outputs(app_handle: &AppHandle) -> HashSet<PathBuf> {
+    let state = app_handle.state::<ConversionState>();
+    let conversions = state.lock().unwrap();
+    active_outputs(&conversions)
+}
+
+fn active_outputs(conversions: &HashMap<String, ConversionProgress>) -> HashSet<PathBuf> {
+    conversions
+        .values()
+        .filter_map(|progress| progress.output_path.as_ref().map(PathBuf::from))
+        .collect()
+}
+
+/// Handle to a tracked job, shared between the command that started it and its worker task.
+#[derive(Clone)]
+pub struct JobContext {
+    pub id: String,
+    state: ConversionState,
+    process_handles: ProcessHandles,
+    app_handle: AppHandle,
+}
+
+impl JobContext {
+    /// Registers a new job in the conversion state and returns its context.
+    pub fn register(app_handle: &AppHandle, current_file: &str, output_path: Option<&str>) -> Self {
+        let job = Self::new(app_handle);
+        {
+            let mut conversions = job.state.lock().unwrap();
+            job.insert_progress(&mut conversions, current_file, output_path);
+        }
+        job
+    }
+
+    /// Registers a job that reads `inputs` and writes a single file, applying
+    /// the collision policy to `output_path` before anything is launched.
+    ///
+    /// Files on disk, the job's own inputs and the outputs of other active jobs
+    /// all count as taken. Resolution runs under the state lock, so jobs
+    /// started together in a batch can never pick the same path. Returns the
+    /// job and the path it should write to.
+    pub fn register_output(
+        app_handle: &AppHandle,
+        inputs: &[impl AsRef<Path>],
+        output_path: &str,
+        collision: CollisionSettings,
+    ) -> Result<(Self, String), FileDittoError> {
+        let inputs: Vec<&Path> = inputs.iter().map(AsRef::as_ref).collect();
+        let current_file = inputs
+            .first()
+            .map(|input| input.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let job = Self::new(app_handle);
+        let resolved = {
+            let mut conversions = job.state.lock().unwrap();
+            let reserved = active_outputs(&conversions);
+            let resolved =
+                output::resolve_collision(Path::new(output_path), &inputs, collision, &reserved)?
+                    .to_string_lossy()
+                    .to_string();
+            job.insert_progress(&mut conversions, &current_file, Some(&resolved));
+            resolved
+        };
+
+        if resolved != output_path {
+            info!("Output exists, writing to {} instead", resolved);
+        }
+        Ok((job, resolved))
+    }
+
+    fn new(app_handle: &AppHandle) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            state: app_handle.state::<ConversionState>().inner().clone(),
+            process_handles: app_handle.state::<ProcessHandles>().inner().clone(),
+            app_handle: app_handle.clone(),
+        }
+    }
+
+    fn insert_progress(
+        &self,
+        conversions: &mut HashMap<String, ConversionProgress>,
+        current_file: &str,
+        output_path: Option<&str>,
+    ) {
+        conversions.insert(
+            self.id.clone(),
+            ConversionProgress {
+                id: self.id.clone(),
+                progress: 0.0,
+                status: "Starting".to_string(),
+                current_file: current_file.to_string(),
+                output_path: output_path.map(|p| p.to_string()),
+                eta: None,
+                speed: None,
+            },
+        );
+    }
+
+    /// Short form of the job ID for log lines.
+    pub fn short_id(&self) -> &str {
+        &self.id[..8]
+    }
+
+    /// Updates the job status and emits a progress event.
+    ///
+    /// A pending cancellation is never overwritten so the worker can still observe it.
+    pub fn set_status(&self, status: &str) {
+        self.update(|progress| {
+            if progress.status != "Cancelling" {
+                progress.status = status.to_string();
+            }
+        });
+    }
+
+    /// Updates the overall progress percentage and emits a progress event.
+    pub fn set_progress(&self, percent: f32, eta: Option<String>, speed: Option<String>) {
+        self.update(|progress| {
+            progress.progress = percent.clamp(0.0, 100.0);
+            progress.eta = eta;
+            progress.speed = speed;
+        });
+    }
+
+    /// Returns true once `cancel_conversion` has been requested for this job.
+    pub fn is_cancelled(&self) -> bool {
+        let conversions = self.state.lock().unwrap();
+        conversions
+            .get(&self.id)
+            .map(|progress| progress.status == "Cancelling")
+            .unwrap_or(false)
+    }
+
+    /// Runs an FFmpeg command to completion while publishing its progress.
+    ///
+    /// `duration` is the expected output duration in seconds and `span` is the
+    /// slice of the job's overall 0-100 progress this run covers, so multi-step
+    /// jobs can report a single continuous progress bar.
+    pub fn run_ffmpeg(
+        &self,
+        cmd: &mut Command,
+        duration: Option<f64>,
+        span: (f32, f32),
+    ) -> Result<String> {
+        if self.is_cancelled() {
+            return Err(cancelled().into());
+        }
+
+        cmd.args(["-progress", "pipe:1", "-nostats"]);
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+        info!(command = ?cmd, "Executing FFmpeg");
+
+        let mut child = cmd.spawn().map_err(|e| {
+            info!("Check if FFmpeg is properly installed and accessible");
+            let message = format!("Failed to start FFmpeg process: {}", e);
+            match e.kind() {
+                io::ErrorKind::NotFound => FileDittoError::FfmpegNotFound(message.into()),
+                _ => FileDittoError::from_io(&e, "Failed to start FFmpeg process"),
+            }
+        })?;
+
+        {
+            let mut handles = self.process_handles.lock().unwrap();
+            handles.insert(self.id.clone(), child.id());
+        }
+        self.set_status("Converting");
+
+        // Drain stderr on its own thread so a chatty FFmpeg never blocks on a full pipe.
+        let mut stderr = child.stderr.take().expect("stderr is piped");
+        let stderr_reader = std::thread::spawn(move || {
+            let mut buffer = String::new();
+            let _ = stderr.read_to_string(&mut buffer);
+            buffer
+        });
+
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let mut speed: Option<f64> = None;
+        for line in BufReader::new(stdout).lines().map_while(|l| l.ok()) {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            match key {
+                "speed" => speed = parse_speed(value),
+                "out_time_us" | "out_time_ms" => {
+                    let (Some(total), Ok(out_time_us)) = (duration, value.trim().parse::<f64>())
+                    else {
+                        continue;
+                    };
+                    if total <= 0.0 {
+                        continue;
+                    }
+                    let elapsed = out_time_us / 1_000_000.0;
+                    let fraction = (elapsed / total).clamp(0.0, 1.0) as f32;
+                    let eta = speed
+                        .filter(|s| *s > 0.0)
+                        .map(|s| format_eta((total - elapsed).max(0.0) / s));
+                    self.set_progress(
+                        span.0 + (span.1 - span.0) * fraction,
+                        eta,
+                        speed.map(|s| format!("{:.2}x", s)),
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        let status = child
+            .wait()
+            .map_err(|e| anyhow!("FFmpeg process failed to complete: {}", e))?;
+        let stderr_output = stderr_reader.join().unwrap_or_default();
+        if let Err(e) = logging::append_job_log(
+            &self.app_handle,
+            &self.id,
+            &format!("{:?}", cmd),
+            &stderr_output,
+        ) {
+            warn!("Could not write job log: {}", e);
+        }
+
+        {
+            let mut handles = self.process_handles.lock().unwrap();
+            handles.remove(&self.id);
+        }
+
+        info!(exit_code = ?status.code(), "FFmpeg process completed");
+
+        if self.is_cancelled() {
+            return Err(cancelled().into());
+        }
+
+        if !status.success() {
+            let error = FileDittoError::from_ffmpeg_stderr(&stderr_output);
+            error!(stderr = %stderr_output, "FFmpeg run failed: {}", error);
+            return Err(error.into());
+        }
+
+        debug!(stderr = %stderr_output, "FFmpeg output");
+
+        self.set_progress(span.1, None, None);
+        Ok(stderr_output)
+    }
+
+    /// Removes the job from conversion and process tracking.
+    pub fn finish(&self) {
+        {
+            let mut conversions = self.state.lock().unwrap();
+            conversions.remove(&self.id);
+        }
+        {
+            let mut handles = self.process_handles.lock().unwrap();
+            handles.remove(&self.id);
+        }
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut ConversionProgress)) {
+        let mut conversions = self.state.lock().unwrap();
+        if let Some(progress) = conversions.get_mut(&self.id) {
+            apply(progress);
+            let _ = self
+                .app_handle
+                .emit("conversion_progress", progress.clone());
+        }
+    }
+}
+
+/// Runs `task` in the background and emits `conversion_complete` with its outcome.
+///
+/// The task returns the primary output path on success. The job is removed from
+/// tracking whether it succeeds or fails.
+pub fn spawn_job<F, Fut>(job: JobContext, task: F)
+where
+    F: FnOnce(JobContext) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<String>> + Send + 'static,
+{
+    // Every log line from the job carries its conversion ID
+    let span = info_span!("job", conversion_id = %job.id);
+    let task = async move {
+        let result = task(job.clone()).await;
+        job.finish();
+
+        if let Err(e) = &result {
+            error!("Job failed: {}", e);
+        }
+
+        let conversion_result = ConversionResult {
+            id: job.id.clone(),
+            success: result.is_ok(),
+            output_path: result.as_ref().ok().cloned(),
+            error: result.err().map(FileDittoError::from),
+        };
+
+        let _ = job
+            .app_handle
+            .emit("conversion_complete", conversion_result);
+    };
+    tokio::spawn(task.instrument(span));
+}
+
+fn cancelled() -> FileDittoError {
+    FileDittoError::Cancelled("Conversion cancelled".into())
+}
+
+/// Parses FFmpeg's `speed=1.23x` progress value.
+fn parse_speed(value: &str) -> Option<f64> {
+    value.trim().trim_end_matches('x').parse::<f64>().ok()
+}
+
+/// Formats a number of seconds as `m:ss` or `h:mm:ss`.
+fn format_eta(seconds: f64) -> String {
+    let total = seconds.round() as u64;
+    let (hours, minutes, secs) = (total / 3600, (total % 3600) / 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{}:{:02}", minutes, secs)
+    }
+}
@@ -6,17 +6,52 @@
 //! - File format conversion with quality options
 //! - Real-time conversion progress tracking
 //! - Batch file processing support
+//! - Poster frame and contact sheet previews
+//! - Palette-optimised GIF and animated WebP export
+//! - Image sequence export and assembly
+//! - Joining multiple inputs into one output
+//! - Splitting recordings by duration, size or chapters
+//! - Timelapse and slideshow creation
+//! - HLS and DASH adaptive streaming packaging
+//! - Device and platform target presets
+//! - HEVC, AV1 and VP9 encoding with encoder probing
+//! - FFmpeg build capability reporting
+//! - Custom FFmpeg/FFprobe binary selection and discovery
+//! - Structured, machine-readable command errors
+//! - Rotating log files and diagnostics bundle export
+//! - Per-job FFmpeg log capture
+//! - Output filename collision handling
+//! - Output path generation with filename templates
+//! - Mirrored folder structure and per-format output subfolders
+//! - Recursive folder input with glob, type, size and duration filters
 
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use tauri::Manager;
 
 // Module declarations
+mod animation;
+mod concat;
 mod conversion;
 mod conversion_settings;
+mod creation;
+mod error;
 mod ffmpeg;
+mod filters;
+mod job;
+mod logging;
 mod metadata;
+mod output;
 mod path;
+mod presets;
+mod scan;
+mod sequence;
 mod settings;
+mod split;
+mod streaming;
+#[cfg(test)]
+mod test_util;
+mod thumbnail;
 mod types;
 
 // Re-export types for easier access
@@ -26,6 +61,8 @@ pub use types::*;
 pub fn run() {
     let conversion_state: ConversionState = Arc::new(Mutex::new(HashMap::new()));
     let process_handles: ProcessHandles = Arc::new(Mutex::new(HashMap::new()));
+    let capabilities: CapabilitiesCache = Arc::new(Mutex::new(None));
+    let binary_resolver = path::resolver();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
@@ -34,16 +71,59 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .manage(conversion_state)
         .manage(process_handles)
+        .manage(capabilities)
+        .manage(binary_resolver)
+        .setup(|app| {
+            match logging::init(app.handle()) {
+                Ok(guard) => {
+                    app.manage(guard);
+                }
+                Err(e) => eprintln!("Failed to initialise logging: {}", e),
+            }
+            if let Ok(user_settings) = settings::UserSettings::load(app.handle()) {
+                if let Err(e) =
+                    logging::prune_job_logs(app.handle(), user_settings.log_retention_days)
+                {
+                    tracing::warn!("Could not prune job logs: {}", e);
+                }
+                path::set_custom_binary_paths(user_settings.binary_paths);
+            }
+            ffmpeg::spawn_capability_probe(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             metadata::extract_file_metadata,
+            scan::scan_folders,
             conversion::convert_file,
             conversion::get_conversion_progress,
             conversion::cancel_conversion,
+            output::generate_output_path,
+            animation::convert_to_animation,
+            sequence::export_image_sequence,
+            sequence::import_image_sequence,
+            concat::concat_files,
+            split::split_file,
+            creation::create_timelapse,
+            creation::create_slideshow,
+            streaming::package_streaming,
+            presets::list_device_presets,
+            presets::check_device_preset,
             ffmpeg::check_ffmpeg_availability,
+            ffmpeg::list_video_codecs,
+            ffmpeg::get_ffmpeg_capabilities,
             settings::load_user_settings,
             settings::save_user_settings,
             settings::reset_user_settings,
-            path::open_file_location
+            path::discover_ffmpeg_binaries,
+            path::validate_ffmpeg_binaries,
+            path::get_binary_paths,
+            path::refresh_binary_paths,
+            logging::get_recent_logs,
+            logging::export_diagnostics_bundle,
+            logging::get_conversion_log,
+            path::open_file_location,
+            thumbnail::generate_poster_frame,
+            thumbnail::generate_contact_sheet
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,266 @@
+//! Structured logging to rotating files in the app log directory, plus
+//! commands for reading recent logs and exporting a diagnostics bundle.
+
+use crate::error::FileDittoError;
+use crate::ffmpeg;
+use crate::path;
+use crate::settings::UserSettings;
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing::info;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// Log files are named `fileditto.<date>.log`.
+const LOG_FILE_PREFIX: &str = "fileditto";
+const LOG_FILE_SUFFIX: &str = "log";
+
+/// Daily log files kept before the oldest is deleted.
+const MAX_LOG_FILES: usize = 7;
+
+/// Lines returned by `get_recent_logs` when no limit is given.
+const DEFAULT_RECENT_LINES: usize = 500;
+
+/// Subdirectory of the log directory holding one FFmpeg log per job.
+const JOB_LOG_DIR: &str = "jobs";
+
+/// Keeps the background log writer alive; dropping it flushes pending lines.
+pub struct LogGuard {
+    _guard: WorkerGuard,
+}
+
+/// Returns the directory log files are written to.
+pub fn log_dir(app_handle: &AppHandle) -> Result<PathBuf> {
+    app_handle
+        .path()
+        .app_log_dir()
+        .map_err(|e| anyhow!("Failed to get app log directory: {}", e))
+}
+
+/// Installs the global subscriber: debug and above to daily rotating files,
+/// info and above to stdout for development.
+pub fn init(app_handle: &AppHandle) -> Result<LogGuard> {
+    let dir = log_dir(app_handle)?;
+    fs::create_dir_all(&dir).map_err(|e| anyhow!("Failed to create log directory: {}", e))?;
+
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(&dir)
+        .map_err(|e| anyhow!("Failed to create log file: {}", e))?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    let file_layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(false)
+        .with_filter(LevelFilter::DEBUG);
+    let console_layer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stdout)
+        .with_filter(LevelFilter::INFO);
+
+    tracing_subscriber::registry()
+        .with(file_layer)
+        .with(console_layer)
+        .try_init()
+        .map_err(|e| anyhow!("Failed to install logger: {}", e))?;
+
+    info!("Logging to {}", dir.display());
+    Ok(LogGuard { _guard: guard })
+}
+
+/// Log files in the log directory, oldest first.
+fn log_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| anyhow!("Failed to read log directory: {}", e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().starts_with(LOG_FILE_PREFIX))
+                .unwrap_or(false)
+        })
+        .collect();
+    // The date in the file name sorts chronologically
+    files.sort();
+    Ok(files)
+}
+
+/// Returns the last `max_lines` log lines across the rotated files, oldest first.
+#[tauri::command]
+pub async fn get_recent_logs(
+    max_lines: Option<usize>,
+    app_handle: AppHandle,
+) -> Result<Vec<String>, FileDittoError> {
+    let max_lines = max_lines.unwrap_or(DEFAULT_RECENT_LINES);
+    let files = log_files(&log_dir(&app_handle)?)?;
+
+    let mut lines: Vec<String> = Vec::new();
+    for file in files.iter().rev() {
+        if lines.len() >= max_lines {
+            break;
+        }
+        let content = fs::read_to_string(file)
+            .map_err(|e| FileDittoError::from_io(&e, "Failed to read log file"))?;
+        let mut file_lines: Vec<String> = content.lines().map(String::from).collect();
+        file_lines.append(&mut lines);
+        lines = file_lines;
+    }
+
+    let start = lines.len().saturating_sub(max_lines);
+    Ok(lines.split_off(start))
+}
+
+/// Writes a zip archive for bug reports to `output_path`, containing system
+/// details, the FFmpeg capability report, resolved binaries, user settings
+/// and all log files. Returns the archive path.
+#[tauri::command]
+pub async fn export_diagnostics_bundle(
+    output_path: String,
+    app_handle: AppHandle,
+) -> Result<String, FileDittoError> {
+    let file = File::create(&output_path)
+        .map_err(|e| FileDittoError::from_io(&e, "Failed to create diagnostics bundle"))?;
+    let mut zip = ZipWriter::new(file);
+
+    let system = json!({
+        "app_version": app_handle.package_info().version.to_string(),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+    });
+    let settings = UserSettings::load(&app_handle).ok();
+    let documents = [
+        ("system.json", system),
+        (
+            "ffmpeg_capabilities.json",
+            json!(ffmpeg::cached_capabilities(&app_handle)),
+        ),
+        ("binaries.json", json!(path::resolver().resolved())),
+        ("settings.json", json!(settings)),
+    ];
+
+    for (name, document) in documents {
+        let content = serde_json::to_vec_pretty(&document)
+            .map_err(|e| anyhow!("Failed to serialize {}: {}", name, e))?;
+        write_zip_entry(&mut zip, name, &content)?;
+    }
+
+    for log_file in log_files(&log_dir(&app_handle)?)? {
+        let content = fs::read(&log_file)
+            .map_err(|e| FileDittoError::from_io(&e, "Failed to read log file"))?;
+        let name = log_file.file_name().unwrap_or_default().to_string_lossy();
+        write_zip_entry(&mut zip, &format!("logs/{}", name), &content)?;
+    }
+
+    zip.finish()
+        .map_err(|e| anyhow!("Failed to write diagnostics bundle: {}", e))?;
+
+    info!("Exported diagnostics bundle to {}", output_path);
+    Ok(output_path)
+}
+
+/// Path of the FFmpeg log for a job.
+///
+/// Only UUIDs are accepted so an ID from the frontend cannot escape the log directory.
+pub fn job_log_path(app_handle: &AppHandle, conversion_id: &str) -> Result<PathBuf> {
+    let id = Uuid::parse_str(conversion_id)
+        .map_err(|_| anyhow!("Invalid conversion ID: {}", conversion_id))?;
+    Ok(log_dir(app_handle)?
+        .join(JOB_LOG_DIR)
+        .join(format!("{}.log", id)))
+}
+
+/// Appends one FFmpeg run, its command line and full stderr, to the job's log.
+pub fn append_job_log(
+    app_handle: &AppHandle,
+    conversion_id: &str,
+    command: &str,
+    stderr_output: &str,
+) -> Result<()> {
+    let path = job_log_path(app_handle, conversion_id)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| anyhow!("Failed to create job log directory: {}", e))?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| anyhow!("Failed to open job log: {}", e))?;
+    writeln!(file, "$ {}\n{}", command, stderr_output.trim_end())
+        .map_err(|e| anyhow!("Failed to write job log: {}", e))?;
+    Ok(())
+}
+
+/// Deletes job logs last modified more than `retention_days` ago.
+pub fn prune_job_logs(app_handle: &AppHandle, retention_days: u32) -> Result<()> {
+    let dir = log_dir(app_handle)?.join(JOB_LOG_DIR);
+    if !dir.exists() {
+        return Ok(());
+    }
+
+    let retention = Duration::from_secs(u64::from(retention_days) * 24 * 60 * 60);
+    let mut removed = 0;
+    for entry in fs::read_dir(&dir).map_err(|e| anyhow!("Failed to read job logs: {}", e))? {
+        let Ok(entry) = entry else { continue };
+        let expired = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > retention);
+        if expired && fs::remove_file(entry.path()).is_ok() {
+            removed += 1;
+        }
+    }
+
+    if removed > 0 {
+        info!(
+            "Removed {} job logs older than {} days",
+            removed, retention_days
+        );
+    }
+    Ok(())
+}
+
+/// Returns the full FFmpeg log of a job, for display or attaching to a ticket.
+#[tauri::command]
+pub async fn get_conversion_log(
+    conversion_id: String,
+    app_handle: AppHandle,
+) -> Result<String, FileDittoError> {
+    let path = job_log_path(&app_handle, &conversion_id)
+        .map_err(|e| FileDittoError::InvalidOptions(e.to_string().into()))?;
+    fs::read_to_string(&path).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            FileDittoError::InputMissing(
+                format!(
+                    "No log for conversion {}; it may have expired or not run FFmpeg yet",
+                    conversion_id
+                )
+                .into(),
+            )
+        } else {
+            FileDittoError::from_io(&e, "Failed to read conversion log")
+        }
+    })
+}
+
+fn write_zip_entry(zip: &mut ZipWriter<File>, name: &str, content: &[u8]) -> Result<()> {
+    zip.start_file(name, SimpleFileOptions::default())
+        .map_err(|e| anyhow!("Failed to add {} to diagnostics bundle: {}", name, e))?;
+    zip.write_all(content)
+        .map_err(|e| anyhow!("Failed to write {} to diagnostics bundle: {}", name, e))?;
+    Ok(())
+}
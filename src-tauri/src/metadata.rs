@@ -1,44 +1,232 @@
 //! File metadata extraction functionality using FFprobe into JSON format.
 
+use crate::error::FileDittoError;
 use crate::path;
 use crate::types::FileMetadata;
+use anyhow::Result;
 use std::path::Path;
 use std::process::Command;
+use tracing::debug;
 
 // Extracts metadata from a media file using FFprobe.
 #[tauri::command]
-pub async fn extract_file_metadata(file_path: String) -> Result<FileMetadata, String> {
-    println!("🔍 Extracting metadata for: {}", file_path);
+pub async fn extract_file_metadata(file_path: String) -> Result<FileMetadata, FileDittoError> {
+    debug!("Extracting metadata for: {}", file_path);
 
-    if !Path::new(&file_path).exists() {
-        return Err(format!("File does not exist: {}", file_path));
+    let json_value = probe_file(&file_path)?;
+
+    Ok(parse_metadata_from_json(&json_value))
+}
+
+/// Numeric media properties for backend operations that need more than display strings.
+#[derive(Debug, Clone, Default)]
+pub struct MediaInfo {
+    /// Duration in seconds.
+    pub duration: Option<f64>,
+    /// File size in bytes.
+    pub size: Option<u64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Clockwise display rotation of the video stream in degrees (0, 90, 180 or 270).
+    pub rotation: Option<u32>,
+    pub streams: Vec<StreamInfo>,
+    pub chapters: Vec<ChapterInfo>,
+}
+
+/// A chapter marker with its start and end in seconds.
+#[derive(Debug, Clone)]
+pub struct ChapterInfo {
+    pub start: f64,
+    pub end: f64,
+    pub title: Option<String>,
+}
+
+/// Codec parameters of a single stream, used to decide whether files can be
+/// joined without re-encoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamInfo {
+    pub codec_type: String,
+    pub codec_name: Option<String>,
+    pub profile: Option<String>,
+    pub width: Option<u64>,
+    pub height: Option<u64>,
+    pub pix_fmt: Option<String>,
+    pub frame_rate: Option<String>,
+    pub sample_rate: Option<String>,
+    pub channels: Option<u64>,
+}
+
+impl MediaInfo {
+    /// Returns true if the file has at least one audio stream.
+    pub fn has_audio(&self) -> bool {
+        self.streams.iter().any(|s| s.codec_type == "audio")
+    }
+}
+
+/// Runs FFprobe on a file and returns its parsed JSON output.
+pub fn probe_file(file_path: &str) -> Result<serde_json::Value> {
+    if !Path::new(file_path).exists() {
+        return Err(FileDittoError::InputMissing(
+            format!("File does not exist: {}", file_path).into(),
+        )
+        .into());
     }
 
     let ffprobe_path = path::ffprobe_path();
 
     let output = Command::new(&ffprobe_path)
-        .args(&[
+        .args([
             "-v",
             "quiet",
             "-print_format",
             "json",
             "-show_format",
             "-show_streams",
-            &file_path,
+            "-show_chapters",
+            file_path,
         ])
         .output()
-        .map_err(|e| format!("Failed to execute ffprobe: {}", e))?;
+        .map_err(|e| {
+            FileDittoError::FfmpegNotFound(format!("Failed to execute ffprobe: {}", e).into())
+        })?;
 
     if !output.status.success() {
         let error = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("FFprobe failed: {}", error));
+        return Err(FileDittoError::ProbeFailed(
+            format!("FFprobe could not read {}", file_path).into(),
+        )
+        .with_log_tail(&error)
+        .into());
     }
 
     let json_output = String::from_utf8_lossy(&output.stdout);
-    let json_value: serde_json::Value = serde_json::from_str(&json_output)
-        .map_err(|e| format!("Failed to parse ffprobe output: {}", e))?;
+    serde_json::from_str(&json_output).map_err(|e| {
+        FileDittoError::ProbeFailed(format!("Failed to parse ffprobe output: {}", e).into()).into()
+    })
+}
 
-    Ok(parse_metadata_from_json(&json_value))
+/// Probes a file and returns its numeric duration and video dimensions.
+pub fn probe_media_info(file_path: &str) -> Result<MediaInfo> {
+    let json_value = probe_file(file_path)?;
+    Ok(parse_media_info_from_json(&json_value))
+}
+
+/// Probes a file once and returns both its display metadata and numeric properties.
+pub fn probe_metadata(file_path: &str) -> Result<(FileMetadata, MediaInfo)> {
+    let json_value = probe_file(file_path)?;
+    Ok((
+        parse_metadata_from_json(&json_value),
+        parse_media_info_from_json(&json_value),
+    ))
+}
+
+/// Parses numeric media properties from FFprobe JSON output.
+fn parse_media_info_from_json(json_value: &serde_json::Value) -> MediaInfo {
+    let video_stream = first_video_stream(json_value);
+    let dimension = |key: &str| {
+        video_stream
+            .and_then(|stream| stream.get(key))
+            .and_then(|v| v.as_u64())
+            .map(|v| v as u32)
+    };
+
+    MediaInfo {
+        duration: json_value
+            .get("format")
+            .and_then(|f| f.get("duration"))
+            .and_then(|d| d.as_str())
+            .and_then(|d| d.parse::<f64>().ok()),
+        size: json_value
+            .get("format")
+            .and_then(|f| f.get("size"))
+            .and_then(|s| s.as_str())
+            .and_then(|s| s.parse::<u64>().ok()),
+        width: dimension("width"),
+        height: dimension("height"),
+        rotation: video_stream.and_then(parse_rotation),
+        streams: json_value
+            .get("streams")
+            .and_then(|s| s.as_array())
+            .map(|streams| streams.iter().map(parse_stream_info).collect())
+            .unwrap_or_default(),
+        chapters: json_value
+            .get("chapters")
+            .and_then(|c| c.as_array())
+            .map(|chapters| chapters.iter().filter_map(parse_chapter_info).collect())
+            .unwrap_or_default(),
+    }
+}
+
+/// Parses one FFprobe chapter entry, skipping chapters without valid times.
+fn parse_chapter_info(chapter: &serde_json::Value) -> Option<ChapterInfo> {
+    let time = |key: &str| {
+        chapter
+            .get(key)
+            .and_then(|v| v.as_str())
+            .and_then(|v| v.parse::<f64>().ok())
+    };
+
+    Some(ChapterInfo {
+        start: time("start_time")?,
+        end: time("end_time")?,
+        title: chapter
+            .get("tags")
+            .and_then(|t| t.get("title"))
+            .and_then(|t| t.as_str())
+            .map(String::from),
+    })
+}
+
+/// Parses the codec parameters of one FFprobe stream entry.
+fn parse_stream_info(stream: &serde_json::Value) -> StreamInfo {
+    let text = |key: &str| stream.get(key).and_then(|v| v.as_str()).map(String::from);
+    let number = |key: &str| stream.get(key).and_then(|v| v.as_u64());
+
+    StreamInfo {
+        codec_type: text("codec_type").unwrap_or_default(),
+        codec_name: text("codec_name"),
+        profile: text("profile"),
+        width: number("width"),
+        height: number("height"),
+        pix_fmt: text("pix_fmt"),
+        frame_rate: text("r_frame_rate"),
+        sample_rate: text("sample_rate"),
+        channels: number("channels"),
+    }
+}
+
+/// Reads a video stream's display rotation and normalises it to clockwise degrees.
+///
+/// Newer FFprobe reports a display matrix in `side_data_list` as a
+/// counter-clockwise angle (e.g. `-90` for a portrait phone clip); older builds
+/// and some containers use a clockwise `rotate` tag instead.
+fn parse_rotation(stream: &serde_json::Value) -> Option<u32> {
+    let side_data = stream
+        .get("side_data_list")
+        .and_then(|l| l.as_array())
+        .and_then(|list| {
+            list.iter()
+                .find_map(|entry| entry.get("rotation").and_then(|r| r.as_f64()))
+        })
+        .map(|r| -r);
+    let tag = stream
+        .get("tags")
+        .and_then(|t| t.get("rotate"))
+        .and_then(|r| r.as_str())
+        .and_then(|r| r.parse::<f64>().ok());
+
+    let degrees = side_data.or(tag)?;
+    let normalised = ((degrees / 90.0).round() as i64 * 90).rem_euclid(360) as u32;
+    Some(normalised)
+}
+
+/// Returns the first video stream in FFprobe JSON output, if any.
+fn first_video_stream(json_value: &serde_json::Value) -> Option<&serde_json::Value> {
+    json_value
+        .get("streams")
+        .and_then(|s| s.as_array())?
+        .iter()
+        .find(|stream| stream.get("codec_type").and_then(|ct| ct.as_str()) == Some("video"))
 }
 
 /// Parses metadata from FFprobe JSON output.
@@ -50,6 +238,7 @@ fn parse_metadata_from_json(json_value: &serde_json::Value) -> FileMetadata {
         codec: None,
         format: None,
         size: None,
+        rotation: None,
     };
 
     // Extract format info
@@ -94,6 +283,8 @@ fn parse_metadata_from_json(json_value: &serde_json::Value) -> FileMetadata {
                     if let Some(codec_name) = stream.get("codec_name").and_then(|cn| cn.as_str()) {
                         metadata.codec = Some(codec_name.to_string());
                     }
+
+                    metadata.rotation = parse_rotation(stream).filter(|r| *r != 0);
                     break;
                 }
             }
@@ -0,0 +1,1038 @@
+//! Output path generation and file handling shared by the jobs that write a
+//! single file.
+
+use crate::conversion;
+use crate::error::FileDittoError;
+use crate::metadata;
+use crate::settings::{
+    CollisionPolicy, CollisionSettings, OutputPathMode, OutputPathSettings, RenameStyle,
+    UserSettings,
+};
+use crate::types::ConversionOptions;
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tauri::AppHandle;
+use tracing::{info, warn};
+
+/// File name template used when the user has not set one.
+pub const DEFAULT_FILENAME_TEMPLATE: &str = "{name}_converted";
+
+/// Frame size in a template, dropped as a whole when the size is unknown.
+const SIZE_TOKEN: &str = "{width}x{height}";
+
+/// Name used when a rendered template sanitizes down to nothing.
+const FALLBACK_FILE_NAME: &str = "output";
+
+/// Device names Windows reserves in every directory, regardless of extension.
+const RESERVED_WINDOWS_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Values substituted into a file name template.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateValues<'a> {
+    /// Input file name without extension.
+    pub name: &'a str,
+    /// Input file extension, without the dot.
+    pub ext: &'a str,
+    pub format: &'a str,
+    pub quality: &'a str,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// 1-based position of the file in its batch.
+    pub index: u32,
+    /// Chapter title of a split part.
+    pub title: Option<&'a str>,
+    /// Zero-padded number of a split part.
+    pub part: Option<&'a str>,
+}
+
+/// Returns the output path for converting `input_path` to `options.output_format`.
+///
+/// `index` is the file's 1-based position in its batch, used by `{index}`.
+#[tauri::command]
+pub async fn generate_output_path(
+    input_path: String,
+    options: ConversionOptions,
+    index: Option<u32>,
+    app_handle: AppHandle,
+) -> Result<String, FileDittoError> {
+    let settings = UserSettings::load(&app_handle)?;
+    let template = &settings.output_path.filename_template;
+
+    // Only probe when the template needs the frame size
+    let info = if template.contains("{width}") || template.contains("{height}") {
+        metadata::probe_media_info(&input_path).ok()
+    } else {
+        None
+    };
+
+    let input = Path::new(&input_path);
+    let name = input.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = input.extension().unwrap_or_default().to_string_lossy();
+    let values = TemplateValues {
+        name: &name,
+        ext: &ext,
+        format: &options.output_format,
+        quality: &options.quality,
+        width: info.as_ref().and_then(|i| i.width),
+        height: info.as_ref().and_then(|i| i.height),
+        index: index.unwrap_or(1),
+        ..Default::default()
+    };
+
+    let path = build_output_path(
+        input,
+        &settings.output_path,
+        options.output_dir.as_deref(),
+        options.source_root.as_deref().map(Path::new),
+        &values,
+    );
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Builds an output path from the path settings and template values.
+///
+/// `output_dir` overrides the directory chosen by the settings. A custom
+/// directory that does not exist falls back to the input's directory. In
+/// `MirrorStructure` mode the input's folder relative to `source_root` is
+/// recreated inside the output directory; inputs outside the root, or with
+/// no root given, land directly in it.
+pub fn build_output_path(
+    input_path: &Path,
+    settings: &OutputPathSettings,
+    output_dir: Option<&str>,
+    source_root: Option<&Path>,
+    values: &TemplateValues,
+) -> PathBuf {
+    let input_dir = input_path.parent().unwrap_or(Path::new("")).to_path_buf();
+    let custom_dir = match settings.mode {
+        OutputPathMode::CustomDirectory | OutputPathMode::MirrorStructure => {
+            settings.custom_directory.as_deref()
+        }
+        OutputPathMode::SameAsInput | OutputPathMode::FormatSubfolder => None,
+    };
+    let base_dir = output_dir
+        .or(custom_dir)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_dir());
+
+    let dir = match (&settings.mode, base_dir) {
+        (OutputPathMode::MirrorStructure, Some(base_dir)) => {
+            base_dir.join(relative_dir(&input_dir, source_root))
+        }
+        (OutputPathMode::FormatSubfolder, None) => {
+            input_dir.join(sanitize_file_name(values.format))
+        }
+        (_, Some(base_dir)) => base_dir,
+        (_, None) => input_dir,
+    };
+
+    let file_name = sanitize_file_name(&render_file_name(&settings.filename_template, values));
+    dir.join(format!("{}.{}", file_name, values.format))
+}
+
+/// The part of `input_dir` below `source_root`, keeping only plain folder
+/// names so the result can never climb out of the output directory.
+fn relative_dir(input_dir: &Path, source_root: Option<&Path>) -> PathBuf {
+    source_root
+        .and_then(|root| input_dir.strip_prefix(root).ok())
+        .map(|relative| {
+            relative
+                .components()
+                .filter_map(|component| match component {
+                    Component::Normal(name) => Some(name),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Substitutes template tokens: `{name}`, `{ext}`, `{format}`, `{quality}`,
+/// `{width}`, `{height}`, `{date}` (YYYY-MM-DD, local time) and `{index}`,
+/// plus `{title}` and `{n}` for split parts when those values are set.
+///
+/// The template is scanned once, so braces inside substituted values are
+/// never read as tokens. Unknown tokens are left as written. A missing width
+/// or height renders empty, except in `{width}x{height}`, which is dropped
+/// together with the separator before it.
+pub fn render_file_name(template: &str, values: &TemplateValues) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(after) = rest.strip_prefix(SIZE_TOKEN) {
+            match (values.width, values.height) {
+                (Some(width), Some(height)) => rendered.push_str(&format!("{}x{}", width, height)),
+                _ => {
+                    if rendered.ends_with(['_', '-', '.', ' ']) {
+                        rendered.pop();
+                    }
+                }
+            }
+            rest = after;
+            continue;
+        }
+
+        let substitution = rest
+            .find('}')
+            .and_then(|end| Some((token_value(&rest[1..end], values)?, end)));
+        match substitution {
+            Some((value, end)) => {
+                rendered.push_str(&value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Value of a single template token, or `None` if the token is unknown.
+fn token_value(token: &str, values: &TemplateValues) -> Option<String> {
+    let dimension = |value: Option<u32>| value.map(|v| v.to_string()).unwrap_or_default();
+    let value = match token {
+        "name" => values.name.to_string(),
+        "ext" => values.ext.to_string(),
+        "format" => values.format.to_string(),
+        "quality" => values.quality.to_string(),
+        "width" => dimension(values.width),
+        "height" => dimension(values.height),
+        "date" => chrono::Local::now().format("%Y-%m-%d").to_string(),
+        "index" => values.index.to_string(),
+        "title" => values.title?.to_string(),
+        "n" => values.part?.to_string(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Makes `name` safe to use as a file name on common platforms.
+///
+/// Illegal and control characters become `_`, trailing dots and spaces are
+/// dropped, and Windows device names such as `CON` get a `_` prefix.
+pub fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let sanitized = sanitized.trim().trim_end_matches(['.', ' ']).to_string();
+
+    if sanitized.is_empty() {
+        return FALLBACK_FILE_NAME.to_string();
+    }
+    let device_name = sanitized.split('.').next().unwrap_or_default();
+    if RESERVED_WINDOWS_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(device_name))
+    {
+        return format!("_{}", sanitized);
+    }
+    sanitized
+}
+
+/// A job's output, written to a temporary sibling file and moved into place
+/// only once it is complete.
+///
+/// The final path therefore never holds a partial file, and an existing file
+/// there survives a failed or cancelled run. Dropping the value without
+/// committing removes the temporary file.
+pub struct AtomicOutput {
+    final_path: PathBuf,
+    temp_path: PathBuf,
+    committed: bool,
+}
+
+impl AtomicOutput {
+    /// Prepares a temporary path next to `output_path`, creating its directory
+    /// if needed. The extension is kept so FFmpeg still infers the container
+    /// from it.
+    pub fn new(output_path: &str, job_id: &str) -> Result<Self> {
+        let final_path = PathBuf::from(output_path);
+        if let Some(dir) = final_path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+        {
+            fs::create_dir_all(dir).map_err(|e| {
+                anyhow!("Failed to create output directory {}: {}", dir.display(), e)
+            })?;
+        }
+        let stem = final_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let temp_name = match final_path.extension() {
+            Some(extension) => format!(".{}.{}.tmp.{}", stem, job_id, extension.to_string_lossy()),
+            None => format!(".{}.{}.tmp", stem, job_id),
+        };
+        let temp_path = final_path.with_file_name(temp_name);
+
+        Ok(Self {
+            final_path,
+            temp_path,
+            committed: false,
+        })
+    }
+
+    /// Path FFmpeg should write to.
+    pub fn temp_path(&self) -> &Path {
+        &self.temp_path
+    }
+
+    /// Path the output is moved to on commit.
+    pub fn final_path(&self) -> &Path {
+        &self.final_path
+    }
+
+    /// Verifies the temporary file and renames it over the final path.
+    ///
+    /// The rename replaces any existing file in one step, so readers see
+    /// either the previous file or the complete new one.
+    pub fn commit(mut self) -> Result<u64> {
+        let size = conversion::verify_output_file(&self.temp_path.to_string_lossy())?;
+        fs::rename(&self.temp_path, &self.final_path).map_err(|e| {
+            anyhow!(
+                "Failed to move output into place at {}: {}",
+                self.final_path.display(),
+                e
+            )
+        })?;
+        self.committed = true;
+
+        info!("Output written: {}", self.final_path.display());
+        Ok(size)
+    }
+}
+
+impl Drop for AtomicOutput {
+    fn drop(&mut self) {
+        if self.committed || !self.temp_path.exists() {
+            return;
+        }
+        match fs::remove_file(&self.temp_path) {
+            Ok(_) => info!("Removed partial output file: {}", self.temp_path.display()),
+            Err(e) => warn!(
+                "Failed to remove partial output file: {} - {}",
+                self.temp_path.display(),
+                e
+            ),
+        }
+    }
+}
+
+/// A job's multi-file output, written into a hidden work directory inside the
+/// output directory and moved into place once the job has finished.
+///
+/// Dropping the value without committing removes the work directory and
+/// whatever was written to it.
+pub struct StagingDir {
+    output_dir: PathBuf,
+    work_dir: PathBuf,
+}
+
+impl StagingDir {
+    /// Creates the output directory and a work directory inside it.
+    pub fn new(output_dir: &str, job_id: &str) -> Result<Self> {
+        let output_dir = PathBuf::from(output_dir);
+        let work_dir = output_dir.join(format!(".fileditto_work_{}", job_id));
+        fs::create_dir_all(&work_dir).map_err(|e| {
+            FileDittoError::from_io(
+                &e,
+                &format!("Failed to create output directory {}", output_dir.display()),
+            )
+        })?;
+
+        Ok(Self {
+            output_dir,
+            work_dir,
+        })
+    }
+
+    /// Directory FFmpeg should write to.
+    pub fn path(&self) -> &Path {
+        &self.work_dir
+    }
+
+    /// Moves every staged file into the output directory, in name order, and
+    /// returns their final paths.
+    ///
+    /// Each file's name is resolved against `collision` first, with the job's
+    /// `inputs` and the names already claimed in this commit counting as
+    /// taken. Skipped files are dropped; any other refusal aborts the commit
+    /// before anything is moved.
+    pub fn commit(self, inputs: &[&Path], collision: CollisionSettings) -> Result<Vec<PathBuf>> {
+        let staged = self.staged_entries()?;
+
+        let mut claimed = HashSet::new();
+        let mut moves = Vec::with_capacity(staged.len());
+        for source in &staged {
+            let requested = self.output_dir.join(source.file_name().unwrap_or_default());
+            match resolve_collision(&requested, inputs, collision, &claimed) {
+                Ok(target) => {
+                    claimed.insert(target.clone());
+                    moves.push((source, target));
+                }
+                Err(FileDittoError::Skipped(reason)) => info!("{}", reason.message),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        if moves.is_empty() {
+            return Err(FileDittoError::Skipped(
+                format!(
+                    "Skipped: all {} files already exist in {}",
+                    staged.len(),
+                    self.output_dir.display()
+                )
+                .into(),
+            )
+            .into());
+        }
+
+        let mut written = Vec::with_capacity(moves.len());
+        for (source, target) in moves {
+            move_into_place(source, &target)?;
+            written.push(target);
+        }
+
+        info!(
+            "{} output files written to: {}",
+            written.len(),
+            self.output_dir.display()
+        );
+        Ok(written)
+    }
+
+    /// Moves everything staged, folders included, into the output directory
+    /// as one package whose files refer to each other by name, such as a
+    /// streaming manifest and its segments. Returns the folder the package
+    /// was written to.
+    ///
+    /// Renaming single files would break the references between them, so a
+    /// clash with any existing entry is resolved for the package as a whole:
+    /// `AutoRename` writes it to a numbered sibling of the output directory
+    /// instead, and `Overwrite` replaces the clashing entries, though never
+    /// one of the job's `inputs`.
+    pub fn commit_package(self, inputs: &[&Path], collision: CollisionSettings) -> Result<PathBuf> {
+        let staged = self.staged_entries()?;
+        let target_in =
+            |dir: &Path, source: &Path| dir.join(source.file_name().unwrap_or_default());
+        let clash = staged
+            .iter()
+            .map(|source| target_in(&self.output_dir, source))
+            .find(|target| target.exists());
+
+        let package_dir = match (clash, collision.policy) {
+            (None, _) => self.output_dir.clone(),
+            (Some(target), CollisionPolicy::Overwrite) => {
+                if let Some(input) = inputs.iter().find(|input| {
+                    staged
+                        .iter()
+                        .any(|source| is_same_file(input, &target_in(&self.output_dir, source)))
+                }) {
+                    return Err(FileDittoError::OutputExists(
+                        format!(
+                            "Output package would replace the input file {}",
+                            input.display()
+                        )
+                        .into(),
+                    )
+                    .into());
+                }
+                info!(
+                    "Replacing existing package entries such as {}",
+                    target.display()
+                );
+                self.output_dir.clone()
+            }
+            (Some(target), CollisionPolicy::Skip) => {
+                return Err(FileDittoError::Skipped(
+                    format!("Skipped: {} already exists", target.display()).into(),
+                )
+                .into())
+            }
+            (Some(target), CollisionPolicy::Ask) => {
+                return Err(FileDittoError::OutputExists(
+                    format!("{} already exists", target.display()).into(),
+                )
+                .into())
+            }
+            (Some(_), CollisionPolicy::AutoRename) => {
+                let dir = (1..)
+                    .map(|n| renamed_dir(&self.output_dir, collision.rename_style, n))
+                    .find(|dir| !dir.exists())
+                    .ok_or_else(|| {
+                        FileDittoError::Internal("No free output folder found".into())
+                    })?;
+                fs::create_dir_all(&dir).map_err(|e| {
+                    FileDittoError::from_io(
+                        &e,
+                        &format!("Failed to create output directory {}", dir.display()),
+                    )
+                })?;
+                info!(
+                    "Output package exists, writing to {} instead",
+                    dir.display()
+                );
+                dir
+            }
+        };
+
+        for source in &staged {
+            let target = target_in(&package_dir, source);
+            remove_existing(&target)?;
+            move_into_place(source, &target)?;
+        }
+
+        info!("Output package written to: {}", package_dir.display());
+        Ok(package_dir)
+    }
+
+    /// Top-level entries of the work directory in name order. Fails if FFmpeg
+    /// wrote nothing.
+    fn staged_entries(&self) -> Result<Vec<PathBuf>> {
+        let mut staged: Vec<PathBuf> = fs::read_dir(&self.work_dir)
+            .map_err(|e| FileDittoError::from_io(&e, "Failed to read staged output"))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        if staged.is_empty() {
+            return Err(FileDittoError::FfmpegFailed(
+                format!("No files were written to {}", self.output_dir.display()).into(),
+            )
+            .into());
+        }
+        staged.sort();
+        Ok(staged)
+    }
+}
+
+fn move_into_place(source: &Path, target: &Path) -> Result<()> {
+    fs::rename(source, target).map_err(|e| {
+        FileDittoError::from_io(
+            &e,
+            &format!("Failed to move output into place at {}", target.display()),
+        )
+        .into()
+    })
+}
+
+/// Removes the file or folder at `path`, if there is one.
+fn remove_existing(path: &Path) -> Result<()> {
+    let removed = match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(_) => return Ok(()),
+    };
+    removed.map_err(|e| {
+        FileDittoError::from_io(&e, &format!("Failed to replace {}", path.display())).into()
+    })
+}
+
+impl Drop for StagingDir {
+    fn drop(&mut self) {
+        if !self.work_dir.exists() {
+            return;
+        }
+        if let Err(e) = fs::remove_dir_all(&self.work_dir) {
+            warn!(
+                "Failed to remove staging directory: {} - {}",
+                self.work_dir.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Applies the collision policy to a requested output path.
+///
+/// A path is taken if a file exists there, it is one of the job's `inputs`,
+/// or it is in `reserved`, the outputs of other active jobs. Overwriting an
+/// input would destroy it mid-read and two jobs may never write the same file
+/// at once, so those are refused even under `Overwrite`.
+pub fn resolve_collision(
+    requested: &Path,
+    inputs: &[&Path],
+    collision: CollisionSettings,
+    reserved: &HashSet<PathBuf>,
+) -> Result<PathBuf, FileDittoError> {
+    let is_input = |path: &Path| inputs.iter().any(|input| is_same_file(input, path));
+    let is_reserved = |path: &Path| reserved.contains(path);
+    let is_taken = |path: &Path| path.exists() || is_input(path) || is_reserved(path);
+
+    if !is_taken(requested) {
+        return Ok(requested.to_path_buf());
+    }
+
+    match collision.policy {
+        CollisionPolicy::Overwrite | CollisionPolicy::Ask if is_input(requested) => {
+            Err(FileDittoError::OutputExists(
+                format!(
+                    "Output {} is the input file; choose another name or folder",
+                    requested.display()
+                )
+                .into(),
+            ))
+        }
+        CollisionPolicy::Overwrite if !is_reserved(requested) => Ok(requested.to_path_buf()),
+        CollisionPolicy::Overwrite => Err(FileDittoError::OutputExists(
+            format!(
+                "Another conversion is already writing to {}",
+                requested.display()
+            )
+            .into(),
+        )),
+        CollisionPolicy::Skip => Err(FileDittoError::Skipped(
+            format!("Skipped: {} already exists", requested.display()).into(),
+        )),
+        CollisionPolicy::Ask => Err(FileDittoError::OutputExists(
+            format!("{} already exists", requested.display()).into(),
+        )),
+        CollisionPolicy::AutoRename => (1..)
+            .map(|n| renamed_path(requested, collision.rename_style, n))
+            .find(|candidate| !is_taken(candidate))
+            .ok_or_else(|| FileDittoError::Internal("No free output name found".into())),
+    }
+}
+
+/// The `n`th alternative name for `path`. Underscore numbering starts at 2,
+/// treating the original as the first.
+fn renamed_path(path: &Path, style: RenameStyle, n: u32) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let suffixed = suffixed_name(&stem, style, n);
+    match path.extension() {
+        Some(extension) => {
+            path.with_file_name(format!("{}.{}", suffixed, extension.to_string_lossy()))
+        }
+        None => path.with_file_name(suffixed),
+    }
+}
+
+/// The `n`th alternative name for the folder at `path`. Folder names have no
+/// extension, so the suffix always goes at the end.
+fn renamed_dir(path: &Path, style: RenameStyle, n: u32) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(suffixed_name(&name, style, n))
+}
+
+fn suffixed_name(name: &str, style: RenameStyle, n: u32) -> String {
+    match style {
+        RenameStyle::Parentheses => format!("{} ({})", name, n),
+        RenameStyle::Underscore => format!("{}_{}", name, n + 1),
+    }
+}
+
+/// Whether `a` and `b` name the same file.
+///
+/// Symlinks and `..` are resolved first. When both files exist their identity
+/// is compared directly, which also catches hard links on Unix. A path that
+/// does not exist yet is compared by name, ignoring case on the platforms
+/// whose default filesystems do.
+pub fn is_same_file(a: &Path, b: &Path) -> bool {
+    if let (Ok(a_meta), Ok(b_meta)) = (fs::metadata(a), fs::metadata(b)) {
+        if let Some(same) = same_identity(&a_meta, &b_meta) {
+            return same;
+        }
+    }
+    paths_match(
+        &normalize(a),
+        &normalize(b),
+        cfg!(any(windows, target_os = "macos")),
+    )
+}
+
+#[cfg(unix)]
+fn same_identity(a: &fs::Metadata, b: &fs::Metadata) -> Option<bool> {
+    use std::os::unix::fs::MetadataExt;
+    Some(a.dev() == b.dev() && a.ino() == b.ino())
+}
+
+/// File identity is not exposed by the standard library elsewhere, so hard
+/// links are not detected there.
+#[cfg(not(unix))]
+fn same_identity(_a: &fs::Metadata, _b: &fs::Metadata) -> Option<bool> {
+    None
+}
+
+/// Canonical form of `path`. For a file that does not exist yet, the parent
+/// directory is canonicalized and the file name appended.
+fn normalize(path: &Path) -> PathBuf {
+    if let Ok(canonical) = fs::canonicalize(path) {
+        return canonical;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => {
+            let parent = if parent.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                parent
+            };
+            fs::canonicalize(parent)
+                .map(|parent| parent.join(name))
+                .unwrap_or_else(|_| path.to_path_buf())
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+fn paths_match(a: &Path, b: &Path, case_insensitive: bool) -> bool {
+    if case_insensitive {
+        a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase()
+    } else {
+        a == b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn settings(policy: CollisionPolicy) -> CollisionSettings {
+        CollisionSettings {
+            policy,
+            rename_style: RenameStyle::Parentheses,
+        }
+    }
+
+    #[test]
+    fn paths_differing_in_case_match_only_when_case_insensitive() {
+        let a = Path::new("/videos/Clip.MP4");
+        let b = Path::new("/videos/clip.mp4");
+        assert!(paths_match(a, b, true));
+        assert!(!paths_match(a, b, false));
+    }
+
+    #[test]
+    fn same_file_through_relative_components() {
+        let dir = TempDir::new();
+        let input = dir.0.join("clip.mp4");
+        fs::write(&input, b"data").unwrap();
+        fs::create_dir(dir.0.join("sub")).unwrap();
+
+        assert!(is_same_file(&input, &dir.0.join("sub/../clip.mp4")));
+        assert!(!is_same_file(&input, &dir.0.join("other.mp4")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_to_input_is_same_file() {
+        let dir = TempDir::new();
+        let input = dir.0.join("clip.mp4");
+        fs::write(&input, b"data").unwrap();
+        let link = dir.0.join("link.mp4");
+        std::os::unix::fs::symlink(&input, &link).unwrap();
+
+        assert!(is_same_file(&input, &link));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_directory_is_resolved_for_new_output() {
+        let dir = TempDir::new();
+        let real = dir.0.join("real");
+        fs::create_dir(&real).unwrap();
+        let linked = dir.0.join("linked");
+        std::os::unix::fs::symlink(&real, &linked).unwrap();
+
+        assert!(is_same_file(&real.join("new.mp4"), &linked.join("new.mp4")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hard_link_to_input_is_same_file() {
+        let dir = TempDir::new();
+        let input = dir.0.join("clip.mp4");
+        fs::write(&input, b"data").unwrap();
+        let link = dir.0.join("hard.mp4");
+        fs::hard_link(&input, &link).unwrap();
+
+        assert!(is_same_file(&input, &link));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn overwrite_refuses_symlinked_input() {
+        let dir = TempDir::new();
+        let input = dir.0.join("clip.mp4");
+        fs::write(&input, b"data").unwrap();
+        let link = dir.0.join("link.mp4");
+        std::os::unix::fs::symlink(&input, &link).unwrap();
+
+        let result = resolve_collision(
+            &link,
+            &[input.as_path()],
+            settings(CollisionPolicy::Overwrite),
+            &HashSet::new(),
+        );
+        assert!(matches!(result, Err(FileDittoError::OutputExists(_))));
+    }
+
+    #[test]
+    fn auto_rename_moves_output_off_input() {
+        let dir = TempDir::new();
+        let input = dir.0.join("clip.mp4");
+        fs::write(&input, b"data").unwrap();
+
+        let resolved = resolve_collision(
+            &input,
+            &[input.as_path()],
+            settings(CollisionPolicy::AutoRename),
+            &HashSet::new(),
+        )
+        .unwrap();
+        assert_eq!(resolved, dir.0.join("clip (1).mp4"));
+    }
+
+    fn path_settings(mode: OutputPathMode, custom_directory: Option<&Path>) -> OutputPathSettings {
+        OutputPathSettings {
+            mode,
+            custom_directory: custom_directory.map(|dir| dir.to_string_lossy().to_string()),
+            filename_template: DEFAULT_FILENAME_TEMPLATE.to_string(),
+        }
+    }
+
+    fn mp4_values() -> TemplateValues<'static> {
+        TemplateValues {
+            name: "clip",
+            ext: "mov",
+            format: "mp4",
+            quality: "high",
+            index: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn relative_dir_keeps_folders_below_root() {
+        assert_eq!(
+            relative_dir(Path::new("/src/trip/day1"), Some(Path::new("/src"))),
+            PathBuf::from("trip/day1")
+        );
+    }
+
+    #[test]
+    fn relative_dir_is_empty_outside_root_or_without_one() {
+        assert_eq!(
+            relative_dir(Path::new("/other/day1"), Some(Path::new("/src"))),
+            PathBuf::new()
+        );
+        assert_eq!(relative_dir(Path::new("/src/day1"), None), PathBuf::new());
+    }
+
+    #[test]
+    fn mirror_structure_recreates_source_folders() {
+        let out = TempDir::new();
+        let settings = path_settings(OutputPathMode::MirrorStructure, Some(&out.0));
+
+        let path = build_output_path(
+            Path::new("/src/trip/day1/clip.mov"),
+            &settings,
+            None,
+            Some(Path::new("/src")),
+            &mp4_values(),
+        );
+        assert_eq!(path, out.0.join("trip/day1/clip_converted.mp4"));
+    }
+
+    #[test]
+    fn mirror_structure_is_flat_outside_root_or_without_one() {
+        let out = TempDir::new();
+        let settings = path_settings(OutputPathMode::MirrorStructure, Some(&out.0));
+        let input = Path::new("/other/day1/clip.mov");
+        let expected = out.0.join("clip_converted.mp4");
+
+        let outside = build_output_path(
+            input,
+            &settings,
+            None,
+            Some(Path::new("/src")),
+            &mp4_values(),
+        );
+        assert_eq!(outside, expected);
+
+        let no_root = build_output_path(input, &settings, None, None, &mp4_values());
+        assert_eq!(no_root, expected);
+    }
+
+    #[test]
+    fn format_subfolder_sits_next_to_input() {
+        let settings = path_settings(OutputPathMode::FormatSubfolder, None);
+
+        let path = build_output_path(
+            Path::new("/src/trip/clip.mov"),
+            &settings,
+            None,
+            None,
+            &mp4_values(),
+        );
+        assert_eq!(path, PathBuf::from("/src/trip/mp4/clip_converted.mp4"));
+    }
+
+    #[test]
+    fn renders_every_token() {
+        let values = TemplateValues {
+            width: Some(1920),
+            height: Some(1080),
+            index: 7,
+            ..mp4_values()
+        };
+
+        assert_eq!(
+            render_file_name(
+                "{name}.{ext}_{format}_{quality}_{width}_{height}_{index}",
+                &values
+            ),
+            "clip.mov_mp4_high_1920_1080_7"
+        );
+        let date = render_file_name("{date}", &values);
+        assert!(chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d").is_ok());
+    }
+
+    #[test]
+    fn substituted_values_are_not_rendered_again() {
+        let values = TemplateValues {
+            name: "take {index} {quality}",
+            ..mp4_values()
+        };
+
+        assert_eq!(
+            render_file_name("{name}_{index}", &values),
+            "take {index} {quality}_1"
+        );
+    }
+
+    #[test]
+    fn unknown_tokens_and_stray_braces_are_kept() {
+        assert_eq!(
+            render_file_name("{name}_{camera}_{", &mp4_values()),
+            "clip_{camera}_{"
+        );
+        assert_eq!(render_file_name("{{name}}", &mp4_values()), "{clip}");
+    }
+
+    #[test]
+    fn size_renders_when_known() {
+        let values = TemplateValues {
+            width: Some(1280),
+            height: Some(720),
+            ..mp4_values()
+        };
+
+        assert_eq!(
+            render_file_name("{name}_{width}x{height}", &values),
+            "clip_1280x720"
+        );
+    }
+
+    #[test]
+    fn unknown_size_is_dropped_with_its_separator() {
+        assert_eq!(
+            render_file_name("{name}_{width}x{height}", &mp4_values()),
+            "clip"
+        );
+        assert_eq!(
+            render_file_name("{width}x{height} {name}", &mp4_values()),
+            " clip"
+        );
+        assert_eq!(render_file_name("{name}_{width}", &mp4_values()), "clip_");
+    }
+
+    #[test]
+    fn sanitize_replaces_illegal_and_control_characters() {
+        assert_eq!(
+            sanitize_file_name("a<b>c:d\"e/f\\g|h?i*j\tk"),
+            "a_b_c_d_e_f_g_h_i_j_k"
+        );
+    }
+
+    #[test]
+    fn sanitize_trims_trailing_dots_and_spaces() {
+        assert_eq!(sanitize_file_name("  clip. . "), "clip");
+    }
+
+    #[test]
+    fn sanitize_falls_back_when_nothing_is_left() {
+        assert_eq!(sanitize_file_name(" ... "), FALLBACK_FILE_NAME);
+        assert_eq!(sanitize_file_name(""), FALLBACK_FILE_NAME);
+    }
+
+    #[test]
+    fn sanitize_prefixes_windows_device_names() {
+        assert_eq!(sanitize_file_name("con"), "_con");
+        assert_eq!(sanitize_file_name("LPT1.backup"), "_LPT1.backup");
+        assert_eq!(sanitize_file_name("console"), "console");
+    }
+
+    /// Stages an HLS-like package: a master playlist and one rendition folder.
+    fn staged_package(output_dir: &Path) -> StagingDir {
+        let staging = StagingDir::new(&output_dir.to_string_lossy(), "test").unwrap();
+        fs::create_dir_all(staging.path().join("720p")).unwrap();
+        fs::write(staging.path().join("master.m3u8"), b"new").unwrap();
+        fs::write(staging.path().join("720p/index.m3u8"), b"new").unwrap();
+        staging
+    }
+
+    #[test]
+    fn package_auto_renames_to_sibling_folder() {
+        let dir = TempDir::new();
+        let output_dir = dir.0.join("stream");
+        fs::create_dir_all(&output_dir).unwrap();
+        fs::write(output_dir.join("master.m3u8"), b"old").unwrap();
+
+        let written = staged_package(&output_dir)
+            .commit_package(&[], settings(CollisionPolicy::AutoRename))
+            .unwrap();
+
+        assert_eq!(written, dir.0.join("stream (1)"));
+        assert_eq!(fs::read(written.join("720p/index.m3u8")).unwrap(), b"new");
+        assert_eq!(fs::read(output_dir.join("master.m3u8")).unwrap(), b"old");
+        assert!(!output_dir.join(".fileditto_work_test").exists());
+    }
+
+    #[test]
+    fn package_overwrite_replaces_clashing_entries() {
+        let dir = TempDir::new();
+        fs::create_dir_all(dir.0.join("720p")).unwrap();
+        fs::write(dir.0.join("720p/segment_000.ts"), b"old").unwrap();
+
+        let written = staged_package(&dir.0)
+            .commit_package(&[], settings(CollisionPolicy::Overwrite))
+            .unwrap();
+
+        assert_eq!(written, dir.0);
+        assert!(!dir.0.join("720p/segment_000.ts").exists());
+        assert_eq!(fs::read(dir.0.join("master.m3u8")).unwrap(), b"new");
+    }
+
+    #[test]
+    fn package_clash_is_refused_under_ask() {
+        let dir = TempDir::new();
+        fs::write(dir.0.join("master.m3u8"), b"old").unwrap();
+
+        let error = staged_package(&dir.0)
+            .commit_package(&[], settings(CollisionPolicy::Ask))
+            .unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<FileDittoError>(),
+            Some(FileDittoError::OutputExists(_))
+        ));
+        assert_eq!(fs::read(dir.0.join("master.m3u8")).unwrap(), b"old");
+    }
+}
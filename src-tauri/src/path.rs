@@ -1,47 +1,178 @@
 //! Utilities for locating FFmpeg binaries on the system and opening file locations.
 
-use anyhow::Context;
+use crate::error::FileDittoError;
+use crate::ffmpeg;
+use crate::settings::BinaryPathSettings;
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
 use std::{
-    env::current_exe,
+    collections::HashSet,
+    env::{self, current_exe},
     path::{Path, PathBuf},
     process::Command,
+    sync::{Arc, OnceLock, RwLock},
 };
-use tauri::command;
+use tauri::{command, AppHandle, State};
+use tracing::{debug, error, info, warn};
 
-/// Returns the path to the FFmpeg executable, prioritizing sidecar over system PATH.
+/// Process-wide binary resolver, shared with Tauri state.
+static RESOLVER: OnceLock<BinaryResolverState> = OnceLock::new();
+
+/// Where a resolved binary came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinarySource {
+    /// Chosen in the user settings.
+    Custom,
+    /// Bundled next to the application executable.
+    Sidecar,
+    /// Looked up by name on the system PATH.
+    Path,
+}
+
+/// A binary location and where it came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedBinary {
+    pub path: PathBuf,
+    pub source: BinarySource,
+}
+
+/// The FFmpeg and FFprobe executables in use.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedBinaries {
+    pub ffmpeg: ResolvedBinary,
+    pub ffprobe: ResolvedBinary,
+}
+
+/// Resolves the FFmpeg and FFprobe locations once and caches them until the
+/// custom paths change or a refresh is requested.
+#[derive(Debug, Default)]
+pub struct BinaryResolver {
+    custom: RwLock<BinaryPathSettings>,
+    resolved: RwLock<Option<ResolvedBinaries>>,
+}
+
+pub type BinaryResolverState = Arc<BinaryResolver>;
+
+impl BinaryResolver {
+    /// Returns the cached locations, resolving them on first use.
+    pub fn resolved(&self) -> ResolvedBinaries {
+        if let Some(resolved) = self.resolved.read().unwrap().as_ref() {
+            return resolved.clone();
+        }
+        self.refresh()
+    }
+
+    /// Resolves both binaries again, e.g. after one was installed or moved.
+    pub fn refresh(&self) -> ResolvedBinaries {
+        let custom = self.custom.read().unwrap().clone();
+        let resolved = ResolvedBinaries {
+            ffmpeg: get_binary_path("ffmpeg", custom.ffmpeg_path.as_deref()),
+            ffprobe: get_binary_path("ffprobe", custom.ffprobe_path.as_deref()),
+        };
+        *self.resolved.write().unwrap() = Some(resolved.clone());
+        resolved
+    }
+
+    /// Replaces the custom binary paths and drops the cached locations.
+    /// Returns true if the paths changed.
+    pub fn set_custom_paths(&self, paths: BinaryPathSettings) -> bool {
+        let mut custom = self.custom.write().unwrap();
+        if *custom == paths {
+            return false;
+        }
+        info!("Using custom binary paths: {:?}", paths);
+        *custom = paths;
+        *self.resolved.write().unwrap() = None;
+        true
+    }
+}
+
+/// Returns the shared binary resolver.
+///
+/// Commands reach it through Tauri state; code without an `AppHandle`, such as
+/// metadata probing inside jobs, goes through `ffmpeg_path` and `ffprobe_path`.
+pub fn resolver() -> BinaryResolverState {
+    RESOLVER.get_or_init(Default::default).clone()
+}
+
+/// An FFmpeg installation found during discovery.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BinaryCandidate {
+    pub ffmpeg_path: String,
+    /// FFprobe in the same directory, if present.
+    pub ffprobe_path: Option<String>,
+    /// Version string reported by `ffmpeg -version`, if it ran.
+    pub version: Option<String>,
+    /// Where the candidate was found: `sidecar`, `path` or `common_location`.
+    pub source: String,
+}
+
+/// Outcome of checking that an FFmpeg and FFprobe pair works.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BinaryPairValidation {
+    pub ffmpeg_version: String,
+    pub ffprobe_version: String,
+    pub warnings: Vec<String>,
+}
+
+/// Returns the path to the FFmpeg executable, prioritizing a custom path, then sidecar, then system PATH.
 pub fn ffmpeg_path() -> PathBuf {
-    get_binary_path("ffmpeg")
+    resolver().resolved().ffmpeg.path
 }
 
-/// Returns the path to the FFprobe executable, prioritizing sidecar over system PATH.
+/// Returns the path to the FFprobe executable, prioritizing a custom path, then sidecar, then system PATH.
 pub fn ffprobe_path() -> PathBuf {
-    get_binary_path("ffprobe")
+    resolver().resolved().ffprobe.path
 }
 
-/// Generic function to get binary path, checking sidecar first then falling back to system PATH.
-fn get_binary_path(binary_name: &str) -> PathBuf {
-    let system_path = Path::new(binary_name).to_path_buf();
+/// Replaces the custom binary paths. Returns true if they changed.
+pub fn set_custom_binary_paths(paths: BinaryPathSettings) -> bool {
+    resolver().set_custom_paths(paths)
+}
+
+/// Generic function to get binary path, checking a custom path, then the sidecar, then falling back to system PATH.
+fn get_binary_path(binary_name: &str, custom_path: Option<&str>) -> ResolvedBinary {
+    let system_path = ResolvedBinary {
+        path: Path::new(binary_name).to_path_buf(),
+        source: BinarySource::Path,
+    };
+
+    if let Some(custom_path) = custom_path {
+        let custom_path = PathBuf::from(custom_path);
+        if custom_path.is_file() {
+            info!("Loaded custom {}: {}", binary_name, custom_path.display());
+            return ResolvedBinary {
+                path: custom_path,
+                source: BinarySource::Custom,
+            };
+        }
+        warn!(
+            "Custom {} not found at {}, falling back",
+            binary_name,
+            custom_path.display()
+        );
+    }
 
     match get_sidecar_path(binary_name) {
         Ok(sidecar_path) if sidecar_path.exists() => {
-            println!(
-                "✅ Loaded sidecar {}: {}",
-                binary_name,
-                sidecar_path.display()
-            );
-            sidecar_path
+            info!("Loaded sidecar {}: {}", binary_name, sidecar_path.display());
+            ResolvedBinary {
+                path: sidecar_path,
+                source: BinarySource::Sidecar,
+            }
         }
         Ok(sidecar_path) => {
-            println!(
-                "⚠️ Sidecar {} not found at {}, using default system PATH",
+            warn!(
+                "Sidecar {} not found at {}, using default system PATH",
                 binary_name,
                 sidecar_path.display()
             );
             system_path
         }
         Err(_) => {
-            println!(
-                "⚠️ Could not determine sidecar path for {}, using system PATH",
+            warn!(
+                "Could not determine sidecar path for {}, using system PATH",
                 binary_name
             );
             system_path
@@ -64,53 +195,241 @@ fn get_sidecar_path(binary_name: &str) -> anyhow::Result<PathBuf> {
     Ok(path)
 }
 
+/// Adds the platform's executable extension to a binary name.
+fn executable_name(binary_name: &str) -> String {
+    if cfg!(windows) {
+        format!("{}.exe", binary_name)
+    } else {
+        binary_name.to_string()
+    }
+}
+
+/// Directories where FFmpeg is commonly installed outside of PATH.
+fn common_install_dirs() -> Vec<PathBuf> {
+    if cfg!(target_os = "windows") {
+        let mut dirs = vec![
+            PathBuf::from(r"C:\ffmpeg\bin"),
+            PathBuf::from(r"C:\Program Files\ffmpeg\bin"),
+            PathBuf::from(r"C:\ProgramData\chocolatey\bin"),
+        ];
+        if let Some(profile) = env::var_os("USERPROFILE") {
+            dirs.push(PathBuf::from(&profile).join("scoop").join("shims"));
+        }
+        if let Some(local) = env::var_os("LOCALAPPDATA") {
+            dirs.push(
+                PathBuf::from(local)
+                    .join("Microsoft")
+                    .join("WinGet")
+                    .join("Links"),
+            );
+        }
+        dirs
+    } else if cfg!(target_os = "macos") {
+        ["/opt/homebrew/bin", "/usr/local/bin", "/opt/local/bin"]
+            .iter()
+            .map(PathBuf::from)
+            .collect()
+    } else {
+        [
+            "/usr/bin",
+            "/usr/local/bin",
+            "/opt/ffmpeg/bin",
+            "/snap/bin",
+            "/var/lib/flatpak/exports/bin",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect()
+    }
+}
+
+/// Runs `<binary> -version` and returns the version from the first line,
+/// e.g. `6.1.1` from `ffmpeg version 6.1.1 Copyright ...`.
+fn binary_version(binary_path: &Path) -> anyhow::Result<String> {
+    let output = Command::new(binary_path)
+        .arg("-version")
+        .output()
+        .with_context(|| format!("Failed to execute {}", binary_path.display()))?;
+
+    if !output.status.success() {
+        return Err(anyhow!("{} -version failed", binary_path.display()));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let first_line = stdout.lines().next().unwrap_or_default();
+    first_line
+        .split_once(" version ")
+        .and_then(|(_, rest)| rest.split_whitespace().next())
+        .map(String::from)
+        .ok_or_else(|| {
+            anyhow!(
+                "{} does not look like an FFmpeg tool: {}",
+                binary_path.display(),
+                first_line
+            )
+        })
+}
+
+/// Checks that an FFmpeg and FFprobe pair runs and comes from the same release.
+///
+/// Either path may be `None` to check the binary that would be resolved without it.
+pub fn validate_binary_pair(
+    ffmpeg: Option<&str>,
+    ffprobe: Option<&str>,
+) -> anyhow::Result<BinaryPairValidation> {
+    let ffmpeg = ffmpeg
+        .map(PathBuf::from)
+        .unwrap_or_else(|| get_binary_path("ffmpeg", None).path);
+    let ffprobe = ffprobe
+        .map(PathBuf::from)
+        .unwrap_or_else(|| get_binary_path("ffprobe", None).path);
+
+    let ffmpeg_version = binary_version(&ffmpeg)?;
+    let ffprobe_version = binary_version(&ffprobe)?;
+
+    let mut warnings = Vec::new();
+    if ffmpeg_version != ffprobe_version {
+        warnings.push(format!(
+            "FFmpeg ({}) and FFprobe ({}) versions differ; metadata may not match what FFmpeg reads",
+            ffmpeg_version, ffprobe_version
+        ));
+    }
+
+    Ok(BinaryPairValidation {
+        ffmpeg_version,
+        ffprobe_version,
+        warnings,
+    })
+}
+
+/// Lists FFmpeg installations found in the sidecar location, on PATH and in
+/// common install locations, with their versions.
+#[command]
+pub async fn discover_ffmpeg_binaries() -> Vec<BinaryCandidate> {
+    let mut search_dirs: Vec<(PathBuf, &str)> = Vec::new();
+    if let Ok(sidecar) = get_sidecar_path("ffmpeg") {
+        if let Some(dir) = sidecar.parent() {
+            search_dirs.push((dir.to_path_buf(), "sidecar"));
+        }
+    }
+    if let Some(path_var) = env::var_os("PATH") {
+        search_dirs.extend(env::split_paths(&path_var).map(|dir| (dir, "path")));
+    }
+    search_dirs.extend(
+        common_install_dirs()
+            .into_iter()
+            .map(|dir| (dir, "common_location")),
+    );
+
+    let ffmpeg_name = executable_name("ffmpeg");
+    let ffprobe_name = executable_name("ffprobe");
+    let mut seen = HashSet::new();
+    let mut candidates = Vec::new();
+
+    for (dir, source) in search_dirs {
+        let ffmpeg = dir.join(&ffmpeg_name);
+        if !ffmpeg.is_file() {
+            continue;
+        }
+        // Symlinked installs (e.g. Homebrew) show up under several directories
+        let canonical = ffmpeg.canonicalize().unwrap_or_else(|_| ffmpeg.clone());
+        if !seen.insert(canonical) {
+            continue;
+        }
+
+        let ffprobe = Some(dir.join(&ffprobe_name)).filter(|p| p.is_file());
+
+        candidates.push(BinaryCandidate {
+            ffmpeg_path: ffmpeg.to_string_lossy().to_string(),
+            ffprobe_path: ffprobe.map(|p| p.to_string_lossy().to_string()),
+            version: binary_version(&ffmpeg).ok(),
+            source: source.to_string(),
+        });
+    }
+
+    info!("Found {} FFmpeg installations", candidates.len());
+    candidates
+}
+
+/// Checks that a chosen FFmpeg and FFprobe pair is working.
+#[command]
+pub async fn validate_ffmpeg_binaries(
+    ffmpeg_path: String,
+    ffprobe_path: String,
+) -> Result<BinaryPairValidation, FileDittoError> {
+    validate_binary_pair(Some(&ffmpeg_path), Some(&ffprobe_path)).map_err(FileDittoError::from)
+}
+
+/// Returns the FFmpeg and FFprobe executables in use and where each came from.
+#[command]
+pub async fn get_binary_paths(
+    resolver: State<'_, BinaryResolverState>,
+) -> Result<ResolvedBinaries, FileDittoError> {
+    Ok(resolver.resolved())
+}
+
+/// Resolves the FFmpeg and FFprobe locations again and re-probes the build.
+#[command]
+pub async fn refresh_binary_paths(
+    resolver: State<'_, BinaryResolverState>,
+    app_handle: AppHandle,
+) -> Result<ResolvedBinaries, FileDittoError> {
+    let resolved = resolver.refresh();
+    ffmpeg::spawn_capability_probe(app_handle);
+    Ok(resolved)
+}
+
 /// Opens the file location in the system file explorer.
 ///
 /// On Windows, this uses `explorer /select,"path"` to open Explorer and highlight the file.
 /// On macOS, this uses `open -R "path"` to reveal the file in Finder.
 /// On Linux, this attempts to use xdg-open to open the containing directory.
 #[command]
-pub async fn open_file_location(file_path: String) -> Result<(), String> {
-    println!("🔍 Attempting to open file location for: {}", file_path);
+pub async fn open_file_location(file_path: String) -> Result<(), FileDittoError> {
+    debug!("Attempting to open file location for: {}", file_path);
 
     let path = Path::new(&file_path);
 
     // Check if the file exists
     if !path.exists() {
-        println!("❌ File not found: {}", file_path);
-        return Err(format!("File not found: {}", file_path));
+        error!("File not found: {}", file_path);
+        return Err(FileDittoError::InputMissing(
+            format!("File not found: {}", file_path).into(),
+        ));
     }
 
-    println!("✅ File exists, opening location...");
+    info!("File exists, opening location...");
 
     let result = if cfg!(target_os = "windows") {
         // Windows: Use explorer with /select to highlight the file
-        println!("🪟 Using Windows explorer command");
+        debug!("Using Windows explorer command");
         Command::new("explorer")
             .args(["/select,", &file_path])
             .spawn()
     } else if cfg!(target_os = "macos") {
         // macOS: Use open -R to reveal in Finder
-        println!("🍎 Using macOS open command");
+        debug!("Using macOS open command");
         Command::new("open").args(["-R", &file_path]).spawn()
     } else {
         // Linux: Open the containing directory with xdg-open
-        println!("🐧 Using Linux xdg-open command");
+        debug!("Using Linux xdg-open command");
         if let Some(parent) = path.parent() {
             Command::new("xdg-open").arg(parent).spawn()
         } else {
-            return Err("Cannot determine parent directory".to_string());
+            return Err(FileDittoError::InvalidOptions(
+                "Cannot determine parent directory".into(),
+            ));
         }
     };
 
     match result {
         Ok(_) => {
-            println!("✅ Successfully opened file location");
+            info!("Successfully opened file location");
             Ok(())
         }
         Err(e) => {
-            println!("❌ Failed to open file location: {}", e);
-            Err(format!("Failed to open file location: {}", e))
+            error!("Failed to open file location: {}", e);
+            Err(FileDittoError::from_io(&e, "Failed to open file location"))
         }
     }
 }
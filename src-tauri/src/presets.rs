@@ -0,0 +1,377 @@
+//! Device and platform target presets layered over the format configurations.
+//!
+//! A preset pins everything a target device or service cares about, so users
+//! can pick "iPhone" or "WhatsApp" without knowing codecs, profiles or bitrates.
+
+use crate::error::FileDittoError;
+use crate::ffmpeg;
+use crate::metadata::{self, MediaInfo};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use tauri::AppHandle;
+
+/// Lowest video bitrate a size-limited preset will accept before declaring the
+/// input too long to fit.
+const MIN_VIDEO_BITRATE_KBPS: u32 = 250;
+
+/// Share of a size limit used for the target bitrate, leaving room for
+/// container overhead and rate control overshoot.
+const SIZE_SAFETY_FACTOR: f64 = 0.92;
+
+/// A device or platform target.
+#[derive(Debug, Clone, Serialize)]
+pub struct DevicePreset {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub container: &'static str,
+    pub video_codec: &'static str,
+    pub audio_codec: &'static str,
+    pub profile: &'static str,
+    pub level: &'static str,
+    pub pixel_format: &'static str,
+    /// Maximum length of the frame's long edge in pixels.
+    pub max_width: u32,
+    /// Maximum length of the frame's short edge in pixels.
+    pub max_height: u32,
+    pub max_video_bitrate_kbps: u32,
+    pub audio_bitrate_kbps: u32,
+    /// Hard limit on the output file size, for services that reject larger uploads.
+    pub max_file_size_mb: Option<u32>,
+    /// Move the index to the start of the file so playback can begin while downloading.
+    pub faststart: bool,
+}
+
+/// Outcome of checking whether an input can be converted with a preset.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresetCheck {
+    pub compatible: bool,
+    /// Reasons the input cannot meet the preset.
+    pub errors: Vec<String>,
+    /// Changes the preset will make that the user may want to know about.
+    pub warnings: Vec<String>,
+}
+
+const PRESETS: &[DevicePreset] = &[
+    DevicePreset {
+        id: "iphone",
+        name: "iPhone",
+        description: "Plays on any recent iPhone and iPad",
+        container: "mp4",
+        video_codec: "libx264",
+        audio_codec: "aac",
+        profile: "high",
+        level: "4.2",
+        pixel_format: "yuv420p",
+        max_width: 1920,
+        max_height: 1080,
+        max_video_bitrate_kbps: 10_000,
+        audio_bitrate_kbps: 160,
+        max_file_size_mb: None,
+        faststart: true,
+    },
+    DevicePreset {
+        id: "android",
+        name: "Android",
+        description: "Plays on most Android phones, including older models",
+        container: "mp4",
+        video_codec: "libx264",
+        audio_codec: "aac",
+        profile: "main",
+        level: "4.0",
+        pixel_format: "yuv420p",
+        max_width: 1920,
+        max_height: 1080,
+        max_video_bitrate_kbps: 8_000,
+        audio_bitrate_kbps: 128,
+        max_file_size_mb: None,
+        faststart: true,
+    },
+    DevicePreset {
+        id: "youtube_1080p",
+        name: "YouTube 1080p",
+        description: "Matches YouTube's recommended upload settings for 1080p",
+        container: "mp4",
+        video_codec: "libx264",
+        audio_codec: "aac",
+        profile: "high",
+        level: "4.2",
+        pixel_format: "yuv420p",
+        max_width: 1920,
+        max_height: 1080,
+        max_video_bitrate_kbps: 12_000,
+        audio_bitrate_kbps: 384,
+        max_file_size_mb: None,
+        faststart: true,
+    },
+    DevicePreset {
+        id: "web_small",
+        name: "Web (small)",
+        description: "720p video that streams quickly on web pages",
+        container: "mp4",
+        video_codec: "libx264",
+        audio_codec: "aac",
+        profile: "main",
+        level: "3.1",
+        pixel_format: "yuv420p",
+        max_width: 1280,
+        max_height: 720,
+        max_video_bitrate_kbps: 2_500,
+        audio_bitrate_kbps: 128,
+        max_file_size_mb: None,
+        faststart: true,
+    },
+    DevicePreset {
+        id: "email",
+        name: "Email attachment",
+        description: "Small enough for a 25 MB attachment limit",
+        container: "mp4",
+        video_codec: "libx264",
+        audio_codec: "aac",
+        profile: "main",
+        level: "3.0",
+        pixel_format: "yuv420p",
+        max_width: 854,
+        max_height: 480,
+        max_video_bitrate_kbps: 1_500,
+        audio_bitrate_kbps: 96,
+        max_file_size_mb: Some(25),
+        faststart: true,
+    },
+    DevicePreset {
+        id: "whatsapp",
+        name: "WhatsApp",
+        description: "Fits WhatsApp's 16 MB video limit",
+        container: "mp4",
+        video_codec: "libx264",
+        audio_codec: "aac",
+        profile: "baseline",
+        level: "3.1",
+        pixel_format: "yuv420p",
+        max_width: 1280,
+        max_height: 720,
+        max_video_bitrate_kbps: 2_000,
+        audio_bitrate_kbps: 96,
+        max_file_size_mb: Some(16),
+        faststart: true,
+    },
+];
+
+/// Looks up a preset by its ID.
+pub fn get_preset(id: &str) -> Result<&'static DevicePreset> {
+    PRESETS.iter().find(|p| p.id == id).ok_or_else(|| {
+        let ids: Vec<&str> = PRESETS.iter().map(|p| p.id).collect();
+        FileDittoError::InvalidOptions(
+            format!(
+                "Unknown device preset: '{}'. Available presets: {}",
+                id,
+                ids.join(", ")
+            )
+            .into(),
+        )
+        .into()
+    })
+}
+
+/// Lists the device and platform presets the FFmpeg build can encode.
+///
+/// Presets needing an encoder the build lacks are hidden once the capability
+/// probe has finished.
+#[tauri::command]
+pub async fn list_device_presets(app_handle: AppHandle) -> Vec<DevicePreset> {
+    let capabilities = ffmpeg::cached_capabilities(&app_handle);
+    PRESETS
+        .iter()
+        .filter(|preset| {
+            capabilities
+                .as_ref()
+                .map(|c| c.has_encoder(preset.video_codec) && c.has_encoder(preset.audio_codec))
+                .unwrap_or(true)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Checks whether a file can be converted with a preset.
+///
+/// `speed` is the playback speed factor the conversion will apply, which
+/// changes the output duration the size limit is spread over.
+#[tauri::command]
+pub async fn check_device_preset(
+    file_path: String,
+    preset_id: String,
+    speed: Option<f64>,
+) -> Result<PresetCheck, FileDittoError> {
+    let speed = speed.unwrap_or(1.0);
+    if !(speed.is_finite() && speed > 0.0) {
+        return Err(FileDittoError::InvalidOptions(
+            format!("Speed factor must be positive: {}", speed).into(),
+        ));
+    }
+
+    let preset = get_preset(&preset_id)?;
+    let info = metadata::probe_media_info(&file_path)?;
+    let output_duration = info.duration.map(|duration| duration / speed);
+    Ok(preset.check(&info, output_duration))
+}
+
+impl DevicePreset {
+    /// Checks whether an input can meet this preset's constraints.
+    ///
+    /// `output_duration` is the length of the converted video, which differs
+    /// from the input's when the speed is changed.
+    pub fn check(&self, info: &MediaInfo, output_duration: Option<f64>) -> PresetCheck {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+
+        match (info.width, info.height) {
+            (Some(width), Some(height)) => {
+                let (long_edge, short_edge) = (width.max(height), width.min(height));
+                if long_edge > self.max_width || short_edge > self.max_height {
+                    warnings.push(format!(
+                        "Video will be scaled down from {}x{} to fit {}x{}",
+                        width, height, self.max_width, self.max_height
+                    ));
+                }
+            }
+            _ => errors.push(format!("{} presets need a video stream", self.name)),
+        }
+
+        if let Some(limit_mb) = self.max_file_size_mb {
+            match output_duration {
+                Some(duration) if duration > 0.0 => {
+                    let bitrate = self.size_limited_bitrate(limit_mb, duration);
+                    if bitrate < MIN_VIDEO_BITRATE_KBPS {
+                        errors.push(format!(
+                            "Video is too long to fit in {} MB at watchable quality; trim or split it first",
+                            limit_mb
+                        ));
+                    } else if bitrate < self.max_video_bitrate_kbps {
+                        warnings.push(format!(
+                            "Video bitrate will be limited to {} kbps to stay under {} MB",
+                            bitrate, limit_mb
+                        ));
+                    }
+                }
+                _ => errors.push(format!(
+                    "Could not determine the duration needed to meet the {} MB limit",
+                    limit_mb
+                )),
+            }
+        }
+
+        PresetCheck {
+            compatible: errors.is_empty(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Video filter that fits the frame inside the preset's maximum resolution
+    /// without upscaling, keeping dimensions even for 4:2:0 encoding.
+    ///
+    /// The limits apply to the long and short edges, so portrait phone videos
+    /// are bounded by 1080x1920 rather than squeezed into 1920x1080.
+    pub fn scale_filter(&self) -> String {
+        format!(
+            "scale='if(gte(iw,ih),min({long},iw),min({short},iw))':'if(gte(iw,ih),min({short},ih),min({long},ih))':force_original_aspect_ratio=decrease,scale=trunc(iw/2)*2:trunc(ih/2)*2",
+            long = self.max_width,
+            short = self.max_height
+        )
+    }
+
+    /// Applies this preset's encoder settings to an FFmpeg command.
+    ///
+    /// Fails if the input cannot meet the preset, so impossible targets are
+    /// rejected before FFmpeg runs. The size limit is spread over
+    /// `output_duration`, the length of the converted video.
+    pub fn apply_to_command(
+        &self,
+        cmd: &mut Command,
+        info: Option<&MediaInfo>,
+        output_duration: Option<f64>,
+    ) -> Result<()> {
+        if let Some(info) = info {
+            let check = self.check(info, output_duration);
+            if !check.compatible {
+                return Err(FileDittoError::InvalidOptions(
+                    format!(
+                        "Input cannot be converted for {}: {}",
+                        self.name,
+                        check.errors.join("; ")
+                    )
+                    .into(),
+                )
+                .into());
+            }
+        }
+
+        cmd.args(["-c:v", self.video_codec]);
+        cmd.args(["-profile:v", self.profile, "-level:v", self.level]);
+        cmd.args(["-pix_fmt", self.pixel_format]);
+
+        let size_limited = self
+            .max_file_size_mb
+            .zip(output_duration)
+            .map(|(limit, duration)| self.size_limited_bitrate(limit, duration));
+
+        match size_limited {
+            Some(bitrate) => {
+                let bitrate = bitrate.min(self.max_video_bitrate_kbps);
+                cmd.args(["-b:v", &format!("{}k", bitrate)]);
+                cmd.args(["-maxrate", &format!("{}k", bitrate)]);
+                cmd.args(["-bufsize", &format!("{}k", bitrate * 2)]);
+            }
+            None => {
+                cmd.args(["-preset", "medium", "-crf", "23"]);
+                cmd.args(["-maxrate", &format!("{}k", self.max_video_bitrate_kbps)]);
+                cmd.args(["-bufsize", &format!("{}k", self.max_video_bitrate_kbps * 2)]);
+            }
+        }
+
+        cmd.args(["-c:a", self.audio_codec]);
+        cmd.args(["-b:a", &format!("{}k", self.audio_bitrate_kbps), "-ac", "2"]);
+
+        if self.faststart {
+            cmd.args(["-movflags", "+faststart"]);
+        }
+
+        Ok(())
+    }
+
+    /// Video bitrate that keeps a clip of `duration` seconds under `limit_mb`.
+    fn size_limited_bitrate(&self, limit_mb: u32, duration: f64) -> u32 {
+        let total_kbits = limit_mb as f64 * 8.0 * 1024.0 * SIZE_SAFETY_FACTOR;
+        let total_kbps = total_kbits / duration;
+        (total_kbps - self.audio_bitrate_kbps as f64).max(0.0) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email() -> &'static DevicePreset {
+        get_preset("email").unwrap()
+    }
+
+    #[test]
+    fn size_limit_leaves_room_for_audio() {
+        // 25 MB at 92% over two minutes is 1570 kbps, 96 of them for audio
+        assert_eq!(email().size_limited_bitrate(25, 120.0), 1474);
+    }
+
+    #[test]
+    fn size_limit_below_audio_bitrate_leaves_no_video() {
+        assert_eq!(email().size_limited_bitrate(1, 600.0), 0);
+    }
+
+    #[test]
+    fn scale_filter_bounds_long_and_short_edges() {
+        assert_eq!(
+            email().scale_filter(),
+            "scale='if(gte(iw,ih),min(854,iw),min(480,iw))':'if(gte(iw,ih),min(480,ih),min(854,ih))':force_original_aspect_ratio=decrease,scale=trunc(iw/2)*2:trunc(ih/2)*2"
+        );
+    }
+}
@@ -0,0 +1,498 @@
+//! Recursive folder input: expands directories into the media files they
+//! contain, filtered by type, glob patterns, size and duration.
+
+use crate::error::FileDittoError;
+use crate::metadata;
+use crate::types::FileMetadata;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use tracing::{debug, info, warn};
+use walkdir::{DirEntry, WalkDir};
+
+const VIDEO_EXTENSIONS: &[&str] = &[
+    "mp4", "m4v", "mov", "mkv", "webm", "avi", "flv", "wmv", "mpg", "mpeg", "ts", "mts", "m2ts",
+    "3gp", "ogv",
+];
+const AUDIO_EXTENSIONS: &[&str] = &[
+    "mp3", "wav", "aac", "m4a", "flac", "ogg", "oga", "opus", "wma", "aiff", "aif", "alac",
+];
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "gif", "bmp", "webp", "tif", "tiff", "heic", "avif",
+];
+
+/// Bytes read from a file to recognise its format when the extension is unknown.
+const SNIFF_LENGTH: usize = 32;
+
+/// Sizes of the BMP info headers that follow the 14-byte file header, from
+/// the OS/2 `BITMAPCOREHEADER` to `BITMAPV5HEADER`.
+const BMP_INFO_HEADER_SIZES: &[u32] = &[12, 16, 40, 52, 56, 64, 108, 124];
+
+/// Broad media type of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaKind {
+    Video,
+    Audio,
+    Image,
+}
+
+/// Filters applied while scanning folders.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanOptions {
+    /// Glob patterns matched against the path relative to the scanned folder,
+    /// e.g. `**/*.mov`. Empty includes everything.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Glob patterns for files to leave out, e.g. `**/proxies/**`.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Media types to keep. Empty keeps all three.
+    #[serde(default)]
+    pub media_kinds: Vec<MediaKind>,
+    #[serde(default)]
+    pub min_size_bytes: Option<u64>,
+    #[serde(default)]
+    pub max_size_bytes: Option<u64>,
+    /// Minimum duration in seconds. Applies to video and audio only.
+    #[serde(default)]
+    pub min_duration: Option<f64>,
+    /// Maximum duration in seconds. Applies to video and audio only.
+    #[serde(default)]
+    pub max_duration: Option<f64>,
+    /// Descend into symlinked directories.
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    /// Include files and folders whose names start with a dot.
+    #[serde(default)]
+    pub include_hidden: bool,
+    /// Probe every file and return its metadata. Files are otherwise only
+    /// probed when a duration filter needs it.
+    #[serde(default)]
+    pub include_metadata: bool,
+}
+
+/// A media file found by a folder scan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScannedFile {
+    pub path: String,
+    /// The scanned folder the file was found under, usable as `source_root`.
+    pub root: String,
+    /// Path relative to `root`.
+    pub relative_path: String,
+    pub kind: MediaKind,
+    /// FFprobe metadata, or `None` if it was not requested or the file could
+    /// not be probed.
+    pub metadata: Option<FileMetadata>,
+}
+
+/// Walks `paths` recursively and returns the media files that pass `options`,
+/// in path order. Plain file paths are checked against the same filters.
+#[tauri::command]
+pub async fn scan_folders(
+    paths: Vec<String>,
+    options: ScanOptions,
+) -> Result<Vec<ScannedFile>, FileDittoError> {
+    // Walking a large tree and probing files blocks for a long time
+    tauri::async_runtime::spawn_blocking(move || scan_paths(&paths, &options))
+        .await
+        .map_err(|e| FileDittoError::Internal(format!("Folder scan failed: {}", e).into()))?
+}
+
+fn scan_paths(paths: &[String], options: &ScanOptions) -> Result<Vec<ScannedFile>, FileDittoError> {
+    let include = build_glob_set(&options.include)?;
+    let exclude = build_glob_set(&options.exclude)?;
+
+    let mut seen = HashSet::new();
+    let mut files = Vec::new();
+    for path in paths {
+        let root = Path::new(path);
+        if !root.exists() {
+            return Err(FileDittoError::InputMissing(
+                format!("Folder does not exist: {}", path).into(),
+            ));
+        }
+        // A single file is treated as its own folder's only entry
+        let base = if root.is_dir() {
+            root
+        } else {
+            root.parent().unwrap_or(Path::new(""))
+        };
+
+        let walker = WalkDir::new(root)
+            .follow_links(options.follow_symlinks)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| {
+                options.include_hidden || entry.depth() == 0 || !is_hidden(entry)
+            });
+
+        for entry in walker {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warn!("Skipping unreadable entry while scanning {}: {}", path, e);
+                    continue;
+                }
+            };
+            if !entry.file_type().is_file() || !seen.insert(entry.path().to_path_buf()) {
+                continue;
+            }
+
+            let relative = entry.path().strip_prefix(base).unwrap_or(entry.path());
+            if include.as_ref().is_some_and(|set| !set.is_match(relative))
+                || exclude.as_ref().is_some_and(|set| set.is_match(relative))
+            {
+                continue;
+            }
+
+            if let Some(file) = scan_file(&entry, base, relative, options) {
+                files.push(file);
+            }
+        }
+    }
+
+    info!(
+        "Scanned {} folders, found {} files",
+        paths.len(),
+        files.len()
+    );
+    Ok(files)
+}
+
+/// Classifies and probes one file, returning `None` if a filter rejects it.
+fn scan_file(
+    entry: &DirEntry,
+    root: &Path,
+    relative: &Path,
+    options: &ScanOptions,
+) -> Option<ScannedFile> {
+    let path = entry.path();
+    let kind = classify(path)?;
+    if !options.media_kinds.is_empty() && !options.media_kinds.contains(&kind) {
+        return None;
+    }
+
+    let size = entry.metadata().ok()?.len();
+    if options.min_size_bytes.is_some_and(|min| size < min)
+        || options.max_size_bytes.is_some_and(|max| size > max)
+    {
+        return None;
+    }
+
+    let path_str = path.to_string_lossy().to_string();
+    let duration_filtered = kind != MediaKind::Image
+        && (options.min_duration.is_some() || options.max_duration.is_some());
+    let probed = if options.include_metadata || duration_filtered {
+        match metadata::probe_metadata(&path_str) {
+            Ok(probed) => Some(probed),
+            Err(e) => {
+                debug!("Could not probe {}: {}", path_str, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    if duration_filtered {
+        // Without a known duration the file cannot be shown to pass
+        let duration = probed.as_ref().and_then(|(_, info)| info.duration)?;
+        if options.min_duration.is_some_and(|min| duration < min)
+            || options.max_duration.is_some_and(|max| duration > max)
+        {
+            return None;
+        }
+    }
+
+    Some(ScannedFile {
+        path: path_str,
+        root: root.to_string_lossy().to_string(),
+        relative_path: relative.to_string_lossy().to_string(),
+        kind,
+        metadata: probed.map(|(metadata, _)| metadata),
+    })
+}
+
+/// Classifies a file by extension, falling back to its leading bytes.
+fn classify(path: &Path) -> Option<MediaKind> {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    if VIDEO_EXTENSIONS.contains(&extension.as_str()) {
+        Some(MediaKind::Video)
+    } else if AUDIO_EXTENSIONS.contains(&extension.as_str()) {
+        Some(MediaKind::Audio)
+    } else if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+        Some(MediaKind::Image)
+    } else {
+        sniff(path)
+    }
+}
+
+/// Recognises common media formats from their signatures.
+fn sniff(path: &Path) -> Option<MediaKind> {
+    let mut header = [0u8; SNIFF_LENGTH];
+    let read = File::open(path)
+        .and_then(|mut file| file.read(&mut header))
+        .ok()?;
+    let header = &header[..read];
+
+    let starts = |signature: &[u8]| header.starts_with(signature);
+    let at = |offset: usize, signature: &[u8]| {
+        header
+            .get(offset..offset + signature.len())
+            .is_some_and(|bytes| bytes == signature)
+    };
+
+    if starts(b"RIFF") {
+        return match header.get(8..12)? {
+            b"WEBP" => Some(MediaKind::Image),
+            b"WAVE" => Some(MediaKind::Audio),
+            b"AVI " => Some(MediaKind::Video),
+            _ => None,
+        };
+    }
+    if at(4, b"ftyp") {
+        // MP4 family; audio-only brands are M4A and M4B
+        return match header.get(8..12)? {
+            b"M4A " | b"M4B " => Some(MediaKind::Audio),
+            b"heic" | b"heix" | b"mif1" | b"avif" => Some(MediaKind::Image),
+            _ => Some(MediaKind::Video),
+        };
+    }
+
+    if starts(&[0xFF, 0xD8, 0xFF])
+        || starts(&[0x89, b'P', b'N', b'G'])
+        || starts(b"GIF8")
+        || (starts(b"BM") && is_bmp_header(header))
+        || starts(b"II*\0")
+        || starts(b"MM\0*")
+    {
+        Some(MediaKind::Image)
+    } else if starts(&[0x1A, 0x45, 0xDF, 0xA3]) || starts(&[0x00, 0x00, 0x01, 0xBA]) {
+        // Matroska/WebM or MPEG program stream
+        Some(MediaKind::Video)
+    } else if starts(b"OggS")
+        || starts(b"fLaC")
+        || starts(b"ID3")
+        || (starts(b"FORM") && (at(8, b"AIFF") || at(8, b"AIFC")))
+        || is_mpeg_audio_frame(header)
+    {
+        Some(MediaKind::Audio)
+    } else {
+        None
+    }
+}
+
+/// Whether the BMP info header size at offset 14 is one of the known sizes.
+/// "BM" alone also starts plenty of text files.
+fn is_bmp_header(header: &[u8]) -> bool {
+    header
+        .get(14..18)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .is_some_and(|size| BMP_INFO_HEADER_SIZES.contains(&size))
+}
+
+/// Whether `header` starts with a valid MPEG audio frame header: the 11-bit
+/// frame sync followed by a version, layer, bitrate and sample rate that are
+/// not reserved or invalid.
+fn is_mpeg_audio_frame(header: &[u8]) -> bool {
+    let &[first, second, third, fourth, ..] = header else {
+        return false;
+    };
+    let version = (second >> 3) & 0b11;
+    let layer = (second >> 1) & 0b11;
+    let bitrate_index = third >> 4;
+    let sample_rate_index = (third >> 2) & 0b11;
+    let emphasis = fourth & 0b11;
+
+    first == 0xFF
+        && second & 0xE0 == 0xE0
+        && version != 0b01
+        && layer != 0b00
+        && bitrate_index != 0b1111
+        && sample_rate_index != 0b11
+        && emphasis != 0b10
+}
+
+fn is_hidden(entry: &DirEntry) -> bool {
+    entry.file_name().to_string_lossy().starts_with('.')
+}
+
+/// Compiles glob patterns, or `None` if there are none.
+fn build_glob_set(patterns: &[String]) -> Result<Option<GlobSet>, FileDittoError> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| {
+            FileDittoError::InvalidOptions(format!("Invalid pattern '{}': {}", pattern, e).into())
+        })?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map(Some)
+        .map_err(|e| FileDittoError::InvalidOptions(format!("Invalid patterns: {}", e).into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    /// A header padded with zeros to the sniff length.
+    fn padded(bytes: &[u8]) -> Vec<u8> {
+        let mut header = bytes.to_vec();
+        header.resize(SNIFF_LENGTH, 0);
+        header
+    }
+
+    fn bmp_header(info_header_size: u32) -> Vec<u8> {
+        let mut header = padded(b"BM");
+        header[14..18].copy_from_slice(&info_header_size.to_le_bytes());
+        header
+    }
+
+    fn relative_paths(files: &[ScannedFile]) -> Vec<String> {
+        files
+            .iter()
+            .map(|file| file.relative_path.replace('\\', "/"))
+            .collect()
+    }
+
+    #[test]
+    fn classifies_by_extension_ignoring_case() {
+        assert_eq!(classify(Path::new("clip.MOV")), Some(MediaKind::Video));
+        assert_eq!(classify(Path::new("song.flac")), Some(MediaKind::Audio));
+        assert_eq!(classify(Path::new("photo.JPeg")), Some(MediaKind::Image));
+    }
+
+    #[test]
+    fn classifies_unknown_extensions_by_content() {
+        let dir = TempDir::new();
+        let wav = dir.file("recording.dat", &padded(b"RIFF\0\0\0\0WAVE"));
+        let text = dir.file("notes.txt", b"not media at all");
+        let missing = dir.0.join("missing.bin");
+
+        assert_eq!(classify(&wav), Some(MediaKind::Audio));
+        assert_eq!(classify(&text), None);
+        assert_eq!(classify(&missing), None);
+    }
+
+    #[test]
+    fn sniffs_known_signatures() {
+        let dir = TempDir::new();
+        let cases: &[(&[u8], MediaKind)] = &[
+            (b"RIFF\0\0\0\0WEBPVP8 ", MediaKind::Image),
+            (b"RIFF\0\0\0\0AVI LIST", MediaKind::Video),
+            (b"\0\0\0\x18ftypisom", MediaKind::Video),
+            (b"\0\0\0\x18ftypM4A ", MediaKind::Audio),
+            (b"\0\0\0\x18ftypheic", MediaKind::Image),
+            (&[0x89, b'P', b'N', b'G', 0x0D, 0x0A], MediaKind::Image),
+            (&[0x1A, 0x45, 0xDF, 0xA3], MediaKind::Video),
+            (b"OggS", MediaKind::Audio),
+            (b"FORM\0\0\0\0AIFFCOMM", MediaKind::Audio),
+            (b"FORM\0\0\0\0AIFCFVER", MediaKind::Audio),
+            // MPEG-1 Layer III, 128 kbit/s, 44.1 kHz
+            (&[0xFF, 0xFB, 0x90, 0x64], MediaKind::Audio),
+        ];
+        for (index, (bytes, kind)) in cases.iter().enumerate() {
+            let path = dir.file(&format!("case{}.bin", index), &padded(bytes));
+            assert_eq!(sniff(&path), Some(*kind), "signature {:02X?}", bytes);
+        }
+    }
+
+    #[test]
+    fn sniffs_bmp_only_with_a_known_info_header_size() {
+        let dir = TempDir::new();
+        let bmp = dir.file("image.bin", &bmp_header(40));
+        let text = dir.file("text.bin", &padded(b"BM is also how this note starts"));
+
+        assert_eq!(sniff(&bmp), Some(MediaKind::Image));
+        assert_eq!(sniff(&text), None);
+    }
+
+    #[test]
+    fn rejects_form_containers_that_are_not_aiff() {
+        let dir = TempDir::new();
+        let ilbm = dir.file("picture.bin", &padded(b"FORM\0\0\0\0ILBMBMHD"));
+
+        assert_eq!(sniff(&ilbm), None);
+    }
+
+    #[test]
+    fn rejects_frame_sync_with_reserved_header_fields() {
+        let invalid: &[[u8; 4]] = &[
+            // Reserved version
+            [0xFF, 0xEB, 0x90, 0x64],
+            // Reserved layer
+            [0xFF, 0xF9, 0x90, 0x64],
+            // Invalid bitrate index
+            [0xFF, 0xFB, 0xF0, 0x64],
+            // Reserved sample rate
+            [0xFF, 0xFB, 0x9C, 0x64],
+            // Reserved emphasis
+            [0xFF, 0xFB, 0x90, 0x66],
+        ];
+        for header in invalid {
+            assert!(!is_mpeg_audio_frame(header), "header {:02X?}", header);
+        }
+        assert!(!is_mpeg_audio_frame(&[0xFF, 0xFB]));
+    }
+
+    #[test]
+    fn empty_pattern_list_builds_no_glob_set() {
+        assert!(build_glob_set(&[]).unwrap().is_none());
+    }
+
+    #[test]
+    fn invalid_glob_pattern_is_an_options_error() {
+        let error = build_glob_set(&["**/[".to_string()]).unwrap_err();
+        assert!(matches!(error, FileDittoError::InvalidOptions(_)));
+    }
+
+    #[test]
+    fn include_and_exclude_globs_match_relative_paths() {
+        let dir = TempDir::new();
+        dir.file("shoot/a.mov", b"");
+        dir.file("shoot/b.mp4", b"");
+        dir.file("shoot/proxies/a.mov", b"");
+        dir.file("c.mov", b"");
+
+        let options = ScanOptions {
+            include: vec!["**/*.mov".to_string()],
+            exclude: vec!["**/proxies/**".to_string()],
+            ..Default::default()
+        };
+        let files = scan_paths(&[dir.0.to_string_lossy().to_string()], &options).unwrap();
+
+        assert_eq!(relative_paths(&files), ["c.mov", "shoot/a.mov"]);
+    }
+
+    #[test]
+    fn skips_hidden_entries_and_filters_kinds() {
+        let dir = TempDir::new();
+        dir.file("clip.mp4", b"");
+        dir.file("song.mp3", b"");
+        dir.file(".cache/clip.mp4", b"");
+        dir.file(".hidden.mp4", b"");
+
+        let options = ScanOptions {
+            media_kinds: vec![MediaKind::Video],
+            ..Default::default()
+        };
+        let files = scan_paths(&[dir.0.to_string_lossy().to_string()], &options).unwrap();
+
+        assert_eq!(relative_paths(&files), ["clip.mp4"]);
+        assert!(files[0].metadata.is_none());
+    }
+}
@@ -0,0 +1,459 @@
+//! Image sequence export from videos and video assembly from numbered images.
+
+use crate::conversion_settings;
+use crate::error::FileDittoError;
+use crate::job::{self, JobContext};
+use crate::metadata;
+use crate::output::{AtomicOutput, StagingDir};
+use crate::path;
+use crate::settings::{CollisionPolicy, CollisionSettings};
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::AppHandle;
+use tracing::info;
+
+/// Image formats a sequence can be exported to or assembled from.
+const SEQUENCE_IMAGE_FORMATS: &[&str] = &["png", "jpg", "jpeg", "bmp", "tiff", "webp"];
+
+/// Default naming pattern for exported frames.
+const DEFAULT_NAME_PATTERN: &str = "{name}_{n}";
+
+/// Default number of digits in exported frame numbers.
+const DEFAULT_PADDING: u8 = 5;
+
+/// Which frames of a video to export.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum FrameSelection {
+    EveryFrame,
+    EveryNthFrame { n: u32 },
+    Interval { seconds: f64 },
+}
+
+/// Options for exporting a video to a numbered image sequence.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequenceExportOptions {
+    pub selection: FrameSelection,
+    pub image_format: String,
+    /// File name pattern without extension. `{name}` is the input file stem and
+    /// `{n}` the zero-padded frame number. Defaults to `{name}_{n}`.
+    pub name_pattern: Option<String>,
+    /// Digits in the frame number. Defaults to 5.
+    pub padding: Option<u8>,
+    pub start_number: Option<u32>,
+    /// Overrides the collision policy from settings for frames that already exist.
+    #[serde(default)]
+    pub collision_policy: Option<CollisionPolicy>,
+}
+
+/// Options for assembling a folder of numbered images into a video.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequenceImportOptions {
+    pub frame_rate: f64,
+    pub quality: String,
+    /// Overrides the collision policy from settings, e.g. to retry after `output_exists`.
+    #[serde(default)]
+    pub collision_policy: Option<CollisionPolicy>,
+}
+
+/// A run of numbered images in a folder that FFmpeg can read as one input.
+#[derive(Debug, Clone)]
+struct DetectedSequence {
+    /// printf-style FFmpeg input pattern, e.g. `/frames/shot_%04d.png`.
+    pattern: PathBuf,
+    start_number: u64,
+    frame_count: usize,
+}
+
+/// Starts exporting a video's frames as numbered images into `output_dir`.
+///
+/// The directory is created if needed, and frames that already exist are
+/// handled by the collision policy. Returns the job ID; on completion the
+/// result's `output_path` is the output directory.
+#[tauri::command]
+pub async fn export_image_sequence(
+    file_path: String,
+    output_dir: String,
+    options: SequenceExportOptions,
+    app_handle: AppHandle,
+) -> Result<String, FileDittoError> {
+    validate_image_format(&options.image_format)?;
+
+    let collision = CollisionSettings::load(&app_handle).with_policy(options.collision_policy);
+    let job = JobContext::register(&app_handle, &file_path, Some(&output_dir));
+    let conversion_id = job.id.clone();
+
+    info!(
+        "Starting image sequence export: {} -> {} (Process ID: {})",
+        file_path,
+        output_dir,
+        job.short_id()
+    );
+
+    job::spawn_job(job, move |job| async move {
+        perform_sequence_export(&file_path, &output_dir, &options, collision, &job).await
+    });
+
+    Ok(conversion_id)
+}
+
+/// Starts assembling the numbered images in `input_dir` into a video at `output_path`.
+///
+/// Returns the job ID; progress and completion are reported through the usual
+/// `conversion_progress` and `conversion_complete` events.
+#[tauri::command]
+pub async fn import_image_sequence(
+    input_dir: String,
+    output_path: String,
+    options: SequenceImportOptions,
+    app_handle: AppHandle,
+) -> Result<String, FileDittoError> {
+    if !(options.frame_rate.is_finite() && options.frame_rate > 0.0) {
+        return Err(FileDittoError::InvalidOptions(
+            format!("Invalid frame rate: {}", options.frame_rate).into(),
+        ));
+    }
+
+    let sequence = detect_sequence(Path::new(&input_dir))?;
+
+    let (job, output_path) = JobContext::register_output(
+        &app_handle,
+        &[&input_dir],
+        &output_path,
+        CollisionSettings::load(&app_handle).with_policy(options.collision_policy),
+    )?;
+    let conversion_id = job.id.clone();
+
+    info!(
+        "Starting image sequence import: {} ({} frames) -> {} (Process ID: {})",
+        sequence.pattern.display(),
+        sequence.frame_count,
+        output_path,
+        job.short_id()
+    );
+
+    job::spawn_job(job, move |job| async move {
+        perform_sequence_import(&sequence, &output_path, &options, &job).await
+    });
+
+    Ok(conversion_id)
+}
+
+async fn perform_sequence_export(
+    input_path: &str,
+    output_dir: &str,
+    options: &SequenceExportOptions,
+    collision: CollisionSettings,
+    job: &JobContext,
+) -> Result<String> {
+    let info = metadata::probe_media_info(input_path)?;
+
+    let stem = Path::new(input_path)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let file_name = build_output_pattern(
+        options
+            .name_pattern
+            .as_deref()
+            .unwrap_or(DEFAULT_NAME_PATTERN),
+        &stem,
+        options.padding.unwrap_or(DEFAULT_PADDING),
+    )?;
+    // Frames are staged and moved into place together, so a failed run
+    // leaves no partial sequence behind.
+    let staging = StagingDir::new(output_dir, job.short_id())?;
+    let output_pattern = staging.path().join(format!(
+        "{}.{}",
+        file_name,
+        options.image_format.to_lowercase()
+    ));
+
+    let mut cmd = Command::new(path::ffmpeg_path());
+    cmd.args(["-y", "-i", input_path]);
+    match &options.selection {
+        FrameSelection::EveryFrame => {}
+        FrameSelection::EveryNthFrame { n } => {
+            if *n == 0 {
+                return Err(
+                    FileDittoError::InvalidOptions("Frame step must be at least 1".into()).into(),
+                );
+            }
+            cmd.args(["-vf", &format!("select='not(mod(n\\,{}))'", n)]);
+            cmd.args(["-vsync", "vfr"]);
+        }
+        FrameSelection::Interval { seconds } => {
+            if *seconds <= 0.0 {
+                return Err(FileDittoError::InvalidOptions(
+                    format!("Frame interval must be positive: {}", seconds).into(),
+                )
+                .into());
+            }
+            cmd.args(["-vf", &format!("fps=1/{}", seconds)]);
+        }
+    }
+    if let Some(start_number) = options.start_number {
+        cmd.args(["-start_number", &start_number.to_string()]);
+    }
+    if matches!(options.image_format.to_lowercase().as_str(), "jpg" | "jpeg") {
+        cmd.args(["-q:v", "2"]);
+    }
+    cmd.arg(&output_pattern);
+
+    job.run_ffmpeg(&mut cmd, info.duration, (0.0, 100.0))?;
+
+    staging.commit(&[Path::new(input_path)], collision)?;
+
+    info!("Image sequence written to: {}", output_dir);
+    Ok(output_dir.to_string())
+}
+
+async fn perform_sequence_import(
+    sequence: &DetectedSequence,
+    output_path: &str,
+    options: &SequenceImportOptions,
+    job: &JobContext,
+) -> Result<String> {
+    let output_format = Path::new(output_path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let config = conversion_settings::get_format_config(&output_format, &options.quality)?;
+
+    let mut cmd = Command::new(path::ffmpeg_path());
+    cmd.args(["-y", "-f", "image2"]);
+    cmd.args(["-framerate", &options.frame_rate.to_string()]);
+    cmd.args(["-start_number", &sequence.start_number.to_string()]);
+    cmd.arg("-i").arg(&sequence.pattern);
+    config.apply_to_command(&mut cmd);
+    // Most players cannot decode the 4:4:4 output FFmpeg picks for RGB images,
+    // and 4:2:0 subsampling needs even dimensions.
+    cmd.args(["-vf", "scale=trunc(iw/2)*2:trunc(ih/2)*2"]);
+    cmd.args(["-pix_fmt", "yuv420p"]);
+    let output = AtomicOutput::new(output_path, job.short_id())?;
+    cmd.arg(output.temp_path());
+
+    let duration = sequence.frame_count as f64 / options.frame_rate;
+    job.run_ffmpeg(&mut cmd, Some(duration), (0.0, 100.0))?;
+    output.commit()?;
+
+    Ok(output_path.to_string())
+}
+
+/// Ensures an image format can be used for sequences.
+fn validate_image_format(format: &str) -> Result<()> {
+    if !SEQUENCE_IMAGE_FORMATS.contains(&format.to_lowercase().as_str()) {
+        return Err(FileDittoError::UnsupportedFormat(
+            format!(
+                "Unsupported image format: '{}'. Supported formats: {}",
+                format,
+                SEQUENCE_IMAGE_FORMATS.join(", ")
+            )
+            .into(),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Turns a user naming pattern into an FFmpeg printf-style file name.
+pub fn build_output_pattern(pattern: &str, stem: &str, padding: u8) -> Result<String> {
+    if !pattern.contains("{n}") {
+        return Err(FileDittoError::InvalidOptions(
+            format!(
+                "Naming pattern must contain the {{n}} frame number token: {}",
+                pattern
+            )
+            .into(),
+        )
+        .into());
+    }
+
+    // Literal '%' characters would be read as format specifiers by FFmpeg.
+    let escaped = pattern
+        .replace('%', "%%")
+        .replace("{name}", &stem.replace('%', "%%"));
+    Ok(escaped.replace("{n}", &format!("%0{}d", padding.clamp(1, 9))))
+}
+
+/// Finds the largest run of numbered images in a directory.
+///
+/// Files are grouped by their prefix and extension, so `shot_0001.png`..
+/// `shot_0240.png` becomes `shot_%04d.png` starting at 1 and unpadded
+/// `img1.png`..`img120.png` becomes `img%d.png`. Equally large groups are
+/// decided by name. FFmpeg stops reading at the first missing number, so a
+/// sequence with gaps is rejected.
+fn detect_sequence(dir: &Path) -> Result<DetectedSequence> {
+    if !dir.is_dir() {
+        return Err(FileDittoError::InvalidOptions(
+            format!("Not a directory: {}", dir.display()).into(),
+        )
+        .into());
+    }
+
+    let numbered = Regex::new(r"^(.*?)(\d+)\.([A-Za-z0-9]+)$").unwrap();
+    let mut groups: HashMap<(String, String), Vec<FrameNumber>> = HashMap::new();
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(caps) = numbered.captures(&name) else {
+            continue;
+        };
+        let extension = caps[3].to_string();
+        if !SEQUENCE_IMAGE_FORMATS.contains(&extension.to_lowercase().as_str()) {
+            continue;
+        }
+        let digits = &caps[2];
+        let Ok(number) = digits.parse::<u64>() else {
+            continue;
+        };
+        let zero_padded = digits.len() > 1 && digits.starts_with('0');
+        groups
+            .entry((caps[1].to_string(), extension))
+            .or_default()
+            .push(FrameNumber {
+                number,
+                digits: digits.len(),
+                zero_padded,
+            });
+    }
+
+    let ((prefix, extension), frames) = groups
+        .into_iter()
+        .max_by(|(a_key, a), (b_key, b)| a.len().cmp(&b.len()).then_with(|| b_key.cmp(a_key)))
+        .ok_or_else(|| {
+            FileDittoError::InputMissing(
+                format!("No numbered images found in {}", dir.display()).into(),
+            )
+        })?;
+
+    let number_format = number_format(&frames).ok_or_else(|| {
+        FileDittoError::InvalidOptions(
+            format!(
+                "Frame numbers in {} mix padded and unpadded widths; rename them consistently",
+                dir.display()
+            )
+            .into(),
+        )
+    })?;
+    if let Some(missing) = missing_frame(&frames) {
+        return Err(FileDittoError::InvalidOptions(
+            format!(
+                "Frame {} of the sequence in {} is missing; number the images without gaps",
+                missing,
+                dir.display()
+            )
+            .into(),
+        )
+        .into());
+    }
+    let file_pattern = format!(
+        "{}{}.{}",
+        prefix.replace('%', "%%"),
+        number_format,
+        extension
+    );
+
+    Ok(DetectedSequence {
+        pattern: dir.join(file_pattern),
+        start_number: frames.iter().map(|frame| frame.number).min().unwrap_or(0),
+        frame_count: frames.len(),
+    })
+}
+
+/// The number in a sequence file name, as written.
+struct FrameNumber {
+    number: u64,
+    digits: usize,
+    /// Written with leading zeros, e.g. `0042`.
+    zero_padded: bool,
+}
+
+/// Picks the printf format matching a sequence's numbering.
+///
+/// Zero-padded frames fix the width; wider numbers without leading zeros still
+/// match `%0Nd`. Returns `None` when the numbering fits no single format.
+fn number_format(frames: &[FrameNumber]) -> Option<String> {
+    let mut padded_widths = frames
+        .iter()
+        .filter(|frame| frame.zero_padded)
+        .map(|frame| frame.digits);
+
+    let Some(width) = padded_widths.next() else {
+        return Some("%d".to_string());
+    };
+    let consistent = padded_widths.all(|w| w == width)
+        && frames
+            .iter()
+            .all(|frame| frame.digits == width || (frame.digits > width && !frame.zero_padded));
+    consistent.then(|| format!("%0{}d", width))
+}
+
+/// The first number missing between a sequence's lowest and highest frame.
+fn missing_frame(frames: &[FrameNumber]) -> Option<u64> {
+    let mut numbers: Vec<u64> = frames.iter().map(|frame| frame.number).collect();
+    numbers.sort_unstable();
+    numbers
+        .windows(2)
+        .find(|pair| pair[1] > pair[0] + 1)
+        .map(|pair| pair[0] + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(numbers: &[u64]) -> Vec<FrameNumber> {
+        numbers
+            .iter()
+            .map(|&number| FrameNumber {
+                number,
+                digits: 4,
+                zero_padded: true,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn output_pattern_numbers_frames() {
+        assert_eq!(
+            build_output_pattern("{name}_{n}", "clip", 5).unwrap(),
+            "clip_%05d"
+        );
+        assert_eq!(build_output_pattern("{n}", "clip", 0).unwrap(), "%01d");
+        assert_eq!(build_output_pattern("{n}", "clip", 12).unwrap(), "%09d");
+    }
+
+    #[test]
+    fn output_pattern_escapes_percent_signs() {
+        assert_eq!(
+            build_output_pattern("100%_{name}_{n}", "50%off", 3).unwrap(),
+            "100%%_50%%off_%03d"
+        );
+    }
+
+    #[test]
+    fn output_pattern_requires_frame_number() {
+        assert!(build_output_pattern("{name}", "clip", 5).is_err());
+    }
+
+    #[test]
+    fn contiguous_frames_have_no_gap() {
+        assert_eq!(missing_frame(&frames(&[12, 10, 11, 13])), None);
+    }
+
+    #[test]
+    fn first_gap_is_reported() {
+        assert_eq!(missing_frame(&frames(&[1, 2, 4, 5, 8])), Some(3));
+    }
+}
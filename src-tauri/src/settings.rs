@@ -1,10 +1,15 @@
 //! User settings management with persistent storage.
 
+use crate::error::FileDittoError;
+use crate::ffmpeg;
+use crate::output;
+use crate::path;
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
 
 /// User settings for the application with persistent storage.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,6 +18,80 @@ pub struct UserSettings {
     pub preserve_metadata: bool,
     pub compression_level: u8,
     pub auto_delete: bool,
+    #[serde(default)]
+    pub binary_paths: BinaryPathSettings,
+    /// What to do when an output file already exists.
+    #[serde(default)]
+    pub collision: CollisionSettings,
+    /// Days to keep per-job FFmpeg logs.
+    #[serde(default = "default_log_retention_days")]
+    pub log_retention_days: u32,
+}
+
+/// Longest per-job log retention accepted, in days.
+const MAX_LOG_RETENTION_DAYS: u32 = 365;
+
+fn default_log_retention_days() -> u32 {
+    14
+}
+
+/// Explicit FFmpeg and FFprobe executables, overriding the bundled sidecars.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BinaryPathSettings {
+    pub ffmpeg_path: Option<String>,
+    pub ffprobe_path: Option<String>,
+}
+
+/// How output filename collisions are handled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollisionSettings {
+    #[serde(default)]
+    pub policy: CollisionPolicy,
+    #[serde(default)]
+    pub rename_style: RenameStyle,
+}
+
+impl CollisionSettings {
+    /// The user's collision settings, or the defaults if settings cannot be read.
+    pub fn load(app_handle: &AppHandle) -> Self {
+        UserSettings::load(app_handle)
+            .map(|settings| settings.collision)
+            .unwrap_or_default()
+    }
+
+    /// Replaces the policy with a per-call override, if one is given.
+    pub fn with_policy(mut self, policy: Option<CollisionPolicy>) -> Self {
+        if let Some(policy) = policy {
+            self.policy = policy;
+        }
+        self
+    }
+}
+
+/// What to do when an output path is already taken.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionPolicy {
+    /// Replace the existing file.
+    Overwrite,
+    /// Do not convert the file.
+    Skip,
+    /// Pick a free name with a numbered suffix.
+    #[default]
+    AutoRename,
+    /// Fail with `output_exists` so the UI can ask and retry with another policy.
+    Ask,
+}
+
+/// Suffix format used by `CollisionPolicy::AutoRename`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenameStyle {
+    /// `clip (1).mp4`, `clip (2).mp4`, ...
+    #[default]
+    Parentheses,
+    /// `clip_2.mp4`, `clip_3.mp4`, ...
+    Underscore,
 }
 
 /// Output path configuration options.
@@ -20,6 +99,13 @@ pub struct UserSettings {
 pub struct OutputPathSettings {
     pub mode: OutputPathMode,
     pub custom_directory: Option<String>,
+    /// Output file name without extension. See `output::render_file_name` for tokens.
+    #[serde(default = "default_filename_template")]
+    pub filename_template: String,
+}
+
+fn default_filename_template() -> String {
+    output::DEFAULT_FILENAME_TEMPLATE.to_string()
 }
 
 /// Available output path modes.
@@ -28,6 +114,11 @@ pub struct OutputPathSettings {
 pub enum OutputPathMode {
     SameAsInput,
     CustomDirectory,
+    /// Inside the custom directory, recreating each input's folders below the
+    /// batch's source root.
+    MirrorStructure,
+    /// In a subfolder named after the output format, next to the input.
+    FormatSubfolder,
 }
 
 impl Default for UserSettings {
@@ -36,10 +127,14 @@ impl Default for UserSettings {
             output_path: OutputPathSettings {
                 mode: OutputPathMode::SameAsInput,
                 custom_directory: None,
+                filename_template: default_filename_template(),
             },
             preserve_metadata: true,
             compression_level: 50,
             auto_delete: false,
+            binary_paths: BinaryPathSettings::default(),
+            collision: CollisionSettings::default(),
+            log_retention_days: default_log_retention_days(),
         }
     }
 }
@@ -66,7 +161,7 @@ impl UserSettings {
         let settings_path = Self::get_settings_path(app_handle)?;
 
         if !settings_path.exists() {
-            println!("⚙️ Settings file not found, using defaults");
+            info!("Settings file not found, using defaults");
             return Ok(Self::default());
         }
 
@@ -76,7 +171,7 @@ impl UserSettings {
         let settings: UserSettings = serde_json::from_str(&settings_content)
             .map_err(|e| anyhow!("Failed to parse settings file: {}", e))?;
 
-        println!("⚙️ Loaded user settings from: {}", settings_path.display());
+        info!("Loaded user settings from: {}", settings_path.display());
         Ok(settings)
     }
 
@@ -90,7 +185,7 @@ impl UserSettings {
         fs::write(&settings_path, settings_json)
             .map_err(|e| anyhow!("Failed to write settings file: {}", e))?;
 
-        println!("⚙️ Saved user settings to: {}", settings_path.display());
+        info!("Saved user settings to: {}", settings_path.display());
         Ok(())
     }
 
@@ -104,8 +199,27 @@ impl UserSettings {
             warnings.push("Compression level was above 100, reset to 100".to_string());
         }
 
+        // Validate log retention
+        if self.log_retention_days == 0 {
+            self.log_retention_days = 1;
+            warnings.push("Log retention was 0 days, reset to 1".to_string());
+        } else if self.log_retention_days > MAX_LOG_RETENTION_DAYS {
+            self.log_retention_days = MAX_LOG_RETENTION_DAYS;
+            warnings.push(format!(
+                "Log retention was above {} days, reset to {}",
+                MAX_LOG_RETENTION_DAYS, MAX_LOG_RETENTION_DAYS
+            ));
+        }
+
+        if self.output_path.filename_template.trim().is_empty() {
+            self.output_path.filename_template = default_filename_template();
+            warnings.push("Filename template was empty, reset to the default".to_string());
+        }
+
         // Validate custom directory path if set
-        if let OutputPathMode::CustomDirectory = self.output_path.mode {
+        if let OutputPathMode::CustomDirectory | OutputPathMode::MirrorStructure =
+            self.output_path.mode
+        {
             if let Some(ref custom_dir) = self.output_path.custom_directory {
                 let path = PathBuf::from(custom_dir);
                 if !path.exists() {
@@ -127,22 +241,39 @@ impl UserSettings {
             }
         }
 
+        // Validate custom binary paths if set
+        let binaries = [
+            ("FFmpeg", &self.binary_paths.ffmpeg_path),
+            ("FFprobe", &self.binary_paths.ffprobe_path),
+        ];
+        for (name, binary_path) in binaries {
+            if let Some(binary_path) = binary_path {
+                if !PathBuf::from(binary_path).is_file() {
+                    warnings.push(format!(
+                        "Custom {} binary does not exist: {}. Will fallback to the bundled binary.",
+                        name, binary_path
+                    ));
+                }
+            }
+        }
+
         warnings
     }
 }
 
 /// Tauri command to load user settings.
 #[tauri::command]
-pub async fn load_user_settings(app_handle: AppHandle) -> Result<UserSettings, String> {
-    let mut settings =
-        UserSettings::load(&app_handle).map_err(|e| format!("Failed to load settings: {}", e))?;
+pub async fn load_user_settings(app_handle: AppHandle) -> Result<UserSettings, FileDittoError> {
+    let mut settings = UserSettings::load(&app_handle).map_err(|e| {
+        FileDittoError::InvalidSettings(format!("Failed to load settings: {}", e).into())
+    })?;
 
     let warnings = settings.validate();
     if !warnings.is_empty() {
-        println!("⚠️ Settings validation warnings: {:?}", warnings);
+        warn!("Settings validation warnings: {:?}", warnings);
         // Auto-save corrected settings
         if let Err(e) = settings.save(&app_handle) {
-            println!("⚠️ Failed to save corrected settings: {}", e);
+            warn!("Failed to save corrected settings: {}", e);
         }
     }
 
@@ -154,33 +285,52 @@ pub async fn load_user_settings(app_handle: AppHandle) -> Result<UserSettings, S
 pub async fn save_user_settings(
     settings: UserSettings,
     app_handle: AppHandle,
-) -> Result<(), String> {
+) -> Result<(), FileDittoError> {
     let mut settings = settings;
     let warnings = settings.validate();
 
     if !warnings.is_empty() {
-        println!("⚠️ Settings validation warnings: {:?}", warnings);
-        return Err(format!(
-            "Settings validation failed: {}",
-            warnings.join(", ")
+        warn!("Settings validation warnings: {:?}", warnings);
+        return Err(FileDittoError::InvalidSettings(
+            format!("Settings validation failed: {}", warnings.join(", ")).into(),
         ));
     }
 
+    if settings.binary_paths.ffmpeg_path.is_some() || settings.binary_paths.ffprobe_path.is_some() {
+        path::validate_binary_pair(
+            settings.binary_paths.ffmpeg_path.as_deref(),
+            settings.binary_paths.ffprobe_path.as_deref(),
+        )
+        .map_err(|e| {
+            FileDittoError::InvalidSettings(format!("Settings validation failed: {}", e).into())
+        })?;
+    }
+
     settings
         .save(&app_handle)
-        .map_err(|e| format!("Failed to save settings: {}", e))?;
+        .map_err(|e| FileDittoError::Io(format!("Failed to save settings: {}", e).into()))?;
 
+    apply_binary_paths(&settings, &app_handle);
     Ok(())
 }
 
+/// Points binary resolution at the configured executables and re-probes the
+/// FFmpeg build if they changed.
+pub fn apply_binary_paths(settings: &UserSettings, app_handle: &AppHandle) {
+    if path::set_custom_binary_paths(settings.binary_paths.clone()) {
+        ffmpeg::spawn_capability_probe(app_handle.clone());
+    }
+}
+
 /// Tauri command to reset settings to defaults.
 #[tauri::command]
-pub async fn reset_user_settings(app_handle: AppHandle) -> Result<UserSettings, String> {
+pub async fn reset_user_settings(app_handle: AppHandle) -> Result<UserSettings, FileDittoError> {
     let default_settings = UserSettings::default();
-    default_settings
-        .save(&app_handle)
-        .map_err(|e| format!("Failed to save default settings: {}", e))?;
+    default_settings.save(&app_handle).map_err(|e| {
+        FileDittoError::Io(format!("Failed to save default settings: {}", e).into())
+    })?;
 
-    println!("⚙️ Reset user settings to defaults");
+    apply_binary_paths(&default_settings, &app_handle);
+    info!("Reset user settings to defaults");
     Ok(default_settings)
 }
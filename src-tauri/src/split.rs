@@ -0,0 +1,486 @@
+//! Splitting one recording into several parts by duration, size or chapters.
+//!
+//! Duration and size splits use FFmpeg's segment muxer with stream copy, so
+//! cuts land on the nearest keyframe. Chapter splits run one stream-copy pass
+//! per chapter.
+
+use crate::error::FileDittoError;
+use crate::ffmpeg;
+use crate::job::{self, JobContext};
+use crate::metadata::{self, ChapterInfo, MediaInfo};
+use crate::output::{self, AtomicOutput, StagingDir, TemplateValues};
+use crate::path;
+use crate::sequence;
+use crate::settings::{CollisionPolicy, CollisionSettings};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::AppHandle;
+use tracing::info;
+
+/// Default naming pattern for split parts.
+const DEFAULT_NAME_PATTERN: &str = "{name}_part{n}";
+
+/// Digits in the part number.
+const PART_NUMBER_PADDING: u8 = 3;
+
+/// Share of the size limit targeted per part, leaving headroom for bitrate
+/// spikes and keyframe-aligned cuts.
+const SIZE_SAFETY_FACTOR: f64 = 0.9;
+
+/// How to divide the input.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SplitMode {
+    /// Parts of at most this many minutes.
+    Duration { minutes: f64 },
+    /// Parts of roughly at most this many megabytes.
+    Size { max_mb: f64 },
+    /// One part per chapter marker.
+    Chapters,
+}
+
+/// Options for splitting a file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitOptions {
+    pub mode: SplitMode,
+    /// File name pattern without extension. `{name}` is the input file stem and
+    /// `{n}` the part number; chapter splits also support `{title}`.
+    /// Defaults to `{name}_part{n}`.
+    pub name_pattern: Option<String>,
+    /// Overrides the collision policy from settings for parts that already exist.
+    #[serde(default)]
+    pub collision_policy: Option<CollisionPolicy>,
+}
+
+/// Starts splitting `file_path` into parts written to `output_dir`.
+///
+/// Parts keep the input's container and codecs, and parts that already exist
+/// are handled by the collision policy. Returns the job ID; the result's
+/// `output_path` is the output directory.
+#[tauri::command]
+pub async fn split_file(
+    file_path: String,
+    output_dir: String,
+    options: SplitOptions,
+    app_handle: AppHandle,
+) -> Result<String, FileDittoError> {
+    if !matches!(options.mode, SplitMode::Chapters) {
+        ffmpeg::ensure_features_supported(&app_handle, &["segment"])?;
+    }
+
+    let collision = CollisionSettings::load(&app_handle).with_policy(options.collision_policy);
+    let job = JobContext::register(&app_handle, &file_path, Some(&output_dir));
+    let conversion_id = job.id.clone();
+
+    info!(
+        "Starting split ({:?}): {} -> {} (Process ID: {})",
+        options.mode,
+        file_path,
+        output_dir,
+        job.short_id()
+    );
+
+    job::spawn_job(job, move |job| async move {
+        perform_split(&file_path, &output_dir, &options, collision, &job).await
+    });
+
+    Ok(conversion_id)
+}
+
+async fn perform_split(
+    input_path: &str,
+    output_dir: &str,
+    options: &SplitOptions,
+    collision: CollisionSettings,
+    job: &JobContext,
+) -> Result<String> {
+    let info = metadata::probe_media_info(input_path)?;
+
+    let input = Path::new(input_path);
+    let stem = input
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let extension = input
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .ok_or_else(|| {
+            FileDittoError::UnsupportedFormat(
+                format!("Input file has no extension: {}", input_path).into(),
+            )
+        })?;
+    let pattern = options
+        .name_pattern
+        .as_deref()
+        .unwrap_or(DEFAULT_NAME_PATTERN);
+
+    match &options.mode {
+        SplitMode::Duration { minutes } => {
+            if *minutes <= 0.0 {
+                return Err(FileDittoError::InvalidOptions(
+                    format!("Part length must be positive: {}", minutes).into(),
+                )
+                .into());
+            }
+            run_segmenter(
+                input_path,
+                output_dir,
+                &segment_file_name(pattern, &stem, &extension)?,
+                minutes * 60.0,
+                &info,
+                collision,
+                job,
+            )?;
+        }
+        SplitMode::Size { max_mb } => {
+            let segment_time = segment_time_for_size(*max_mb, &info)?;
+            run_segmenter(
+                input_path,
+                output_dir,
+                &segment_file_name(pattern, &stem, &extension)?,
+                segment_time,
+                &info,
+                collision,
+                job,
+            )?;
+        }
+        SplitMode::Chapters => {
+            if info.chapters.is_empty() {
+                return Err(FileDittoError::InvalidOptions(
+                    format!("File has no chapter markers: {}", input_path).into(),
+                )
+                .into());
+            }
+            let output_paths = chapter_output_paths(
+                input_path, output_dir, pattern, &stem, &extension, &info, collision,
+            )?;
+            split_chapters(input_path, &output_paths, &info, job)?;
+        }
+    }
+
+    info!("Split parts written to: {}", output_dir);
+    Ok(output_dir.to_string())
+}
+
+/// Cuts the input into equal-length parts with the segment muxer.
+///
+/// The muxer opens each part as it goes, so parts are written to a staging
+/// directory and only moved into `output_dir` once the whole run succeeded.
+/// The number of parts is not known up front, so collisions are resolved
+/// when they are moved.
+fn run_segmenter(
+    input_path: &str,
+    output_dir: &str,
+    file_name: &str,
+    segment_time: f64,
+    info: &MediaInfo,
+    collision: CollisionSettings,
+    job: &JobContext,
+) -> Result<()> {
+    let staging = StagingDir::new(output_dir, job.short_id())?;
+    let output_pattern = staging.path().join(file_name);
+
+    let mut cmd = Command::new(path::ffmpeg_path());
+    cmd.args(["-y", "-i", input_path]);
+    cmd.args(["-map", "0:v?", "-map", "0:a?", "-map", "0:s?", "-c", "copy"]);
+    cmd.args(["-f", "segment"]);
+    cmd.args(["-segment_time", &format!("{:.3}", segment_time)]);
+    cmd.args(["-segment_start_number", "1", "-reset_timestamps", "1"]);
+    cmd.arg(&output_pattern);
+
+    job.run_ffmpeg(&mut cmd, info.duration, (0.0, 100.0))?;
+    staging.commit(&[Path::new(input_path)], collision)?;
+    Ok(())
+}
+
+/// Segment muxer file name for `pattern`, with the part number as `%0Nd`.
+fn segment_file_name(pattern: &str, stem: &str, extension: &str) -> Result<String> {
+    let file_name = sequence::build_output_pattern(pattern, stem, PART_NUMBER_PADDING)?;
+    Ok(format!("{}.{}", file_name, extension))
+}
+
+/// Resolves the output path of every chapter part before anything is written.
+///
+/// Returns `None` for chapters without a duration and for chapters the
+/// collision policy skips.
+fn chapter_output_paths(
+    input_path: &str,
+    output_dir: &str,
+    pattern: &str,
+    stem: &str,
+    extension: &str,
+    info: &MediaInfo,
+    collision: CollisionSettings,
+) -> Result<Vec<Option<PathBuf>>> {
+    if !pattern.contains("{n}") && !pattern.contains("{title}") {
+        return Err(FileDittoError::InvalidOptions(
+            format!(
+                "Naming pattern must contain the {{n}} or {{title}} token: {}",
+                pattern
+            )
+            .into(),
+        )
+        .into());
+    }
+
+    let requested: Vec<Option<PathBuf>> = info
+        .chapters
+        .iter()
+        .enumerate()
+        .map(|(index, chapter)| {
+            if chapter.end <= chapter.start {
+                info!("Skipping chapter {} without a duration", index + 1);
+                return None;
+            }
+            Some(chapter_output_path(
+                output_dir, pattern, stem, extension, index, chapter,
+            ))
+        })
+        .collect();
+    let named: Vec<&PathBuf> = requested.iter().flatten().collect();
+    if named.is_empty() {
+        return Err(FileDittoError::InvalidOptions(
+            format!("Chapter markers have no duration: {}", input_path).into(),
+        )
+        .into());
+    }
+    let unique: HashSet<&PathBuf> = named.iter().copied().collect();
+    if unique.len() < named.len() {
+        return Err(FileDittoError::InvalidOptions(
+            "Several chapters share a title; add the {n} token to the naming pattern".into(),
+        )
+        .into());
+    }
+
+    let inputs = [Path::new(input_path)];
+    let mut claimed = HashSet::new();
+    let mut resolved = Vec::with_capacity(requested.len());
+    for path in &requested {
+        let Some(path) = path else {
+            resolved.push(None);
+            continue;
+        };
+        match output::resolve_collision(path, &inputs, collision, &claimed) {
+            Ok(path) => {
+                claimed.insert(path.clone());
+                resolved.push(Some(path));
+            }
+            Err(FileDittoError::Skipped(reason)) => {
+                info!("{}", reason.message);
+                resolved.push(None);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    if resolved.iter().all(Option::is_none) {
+        return Err(FileDittoError::Skipped(
+            format!("Skipped: all chapter parts already exist in {}", output_dir).into(),
+        )
+        .into());
+    }
+    Ok(resolved)
+}
+
+/// Writes one part per chapter, reporting progress across all of them.
+fn split_chapters(
+    input_path: &str,
+    output_paths: &[Option<PathBuf>],
+    info: &MediaInfo,
+    job: &JobContext,
+) -> Result<()> {
+    let total: f64 = info
+        .chapters
+        .iter()
+        .map(|c| (c.end - c.start).max(0.0))
+        .sum();
+    if total <= 0.0 {
+        return Err(FileDittoError::ProbeFailed("Chapter markers have no duration".into()).into());
+    }
+    let mut done = 0.0;
+
+    for (chapter, output_path) in info.chapters.iter().zip(output_paths) {
+        let length = (chapter.end - chapter.start).max(0.0);
+        let span_start = (done / total * 100.0) as f32;
+        done += length;
+        let span_end = (done / total * 100.0) as f32;
+
+        let Some(output_path) = output_path else {
+            continue;
+        };
+        let mut cmd = Command::new(path::ffmpeg_path());
+        cmd.args(["-y", "-ss", &format!("{:.3}", chapter.start)]);
+        cmd.args(["-i", input_path]);
+        cmd.args(["-t", &format!("{:.3}", length)]);
+        cmd.args(["-map", "0:v?", "-map", "0:a?", "-map", "0:s?"]);
+        cmd.args(["-map_chapters", "-1", "-c", "copy"]);
+        let output = AtomicOutput::new(&output_path.to_string_lossy(), job.short_id())?;
+        cmd.arg(output.temp_path());
+
+        job.run_ffmpeg(&mut cmd, Some(length), (span_start, span_end))?;
+        output.commit()?;
+    }
+
+    Ok(())
+}
+
+/// Computes a segment length that keeps parts under `max_mb` at the file's average bitrate.
+fn segment_time_for_size(max_mb: f64, info: &MediaInfo) -> Result<f64> {
+    if max_mb <= 0.0 {
+        return Err(FileDittoError::InvalidOptions(
+            format!("Part size must be positive: {}", max_mb).into(),
+        )
+        .into());
+    }
+
+    let (duration, size) = match (info.duration, info.size) {
+        (Some(d), Some(s)) if d > 0.0 && s > 0 => (d, s as f64),
+        _ => {
+            return Err(FileDittoError::ProbeFailed(
+                "Could not determine file size and duration".into(),
+            )
+            .into())
+        }
+    };
+
+    let bytes_per_second = size / duration;
+    let max_bytes = max_mb * 1024.0 * 1024.0 * SIZE_SAFETY_FACTOR;
+    Ok((max_bytes / bytes_per_second).max(1.0))
+}
+
+/// Builds the output path for one chapter part.
+fn chapter_output_path(
+    output_dir: &str,
+    pattern: &str,
+    stem: &str,
+    extension: &str,
+    index: usize,
+    chapter: &ChapterInfo,
+) -> PathBuf {
+    let title = chapter
+        .title
+        .clone()
+        .unwrap_or_else(|| format!("Chapter {}", index + 1));
+    let part = format!(
+        "{:0width$}",
+        index + 1,
+        width = PART_NUMBER_PADDING as usize
+    );
+    let values = TemplateValues {
+        name: stem,
+        ext: extension,
+        title: Some(&title),
+        part: Some(&part),
+        ..Default::default()
+    };
+    let file_name = output::render_file_name(pattern, &values);
+
+    Path::new(output_dir).join(format!(
+        "{}.{}",
+        output::sanitize_file_name(&file_name),
+        extension
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(start: f64, end: f64, title: &str) -> ChapterInfo {
+        ChapterInfo {
+            start,
+            end,
+            title: Some(title.to_string()),
+        }
+    }
+
+    #[test]
+    fn chapter_names_are_rendered_once() {
+        let path = chapter_output_path(
+            "/out",
+            "{name}_{n}_{title}",
+            "talk {title}",
+            "mkv",
+            1,
+            &chapter(10.0, 20.0, "Q&A {n}"),
+        );
+        assert_eq!(path, PathBuf::from("/out/talk {title}_002_Q&A {n}.mkv"));
+    }
+
+    #[test]
+    fn untitled_chapters_are_numbered_and_titles_sanitized() {
+        let untitled = ChapterInfo {
+            start: 0.0,
+            end: 5.0,
+            title: None,
+        };
+        assert_eq!(
+            chapter_output_path("/out", "{title}", "talk", "mp4", 2, &untitled),
+            PathBuf::from("/out/Chapter 3.mp4")
+        );
+        assert_eq!(
+            chapter_output_path(
+                "/out",
+                "{name} - {title}",
+                "talk",
+                "mp4",
+                0,
+                &chapter(0.0, 5.0, "Intro/Outro?")
+            ),
+            PathBuf::from("/out/talk - Intro_Outro_.mp4")
+        );
+    }
+
+    #[test]
+    fn chapters_without_duration_are_skipped() {
+        let info = MediaInfo {
+            chapters: vec![
+                chapter(0.0, 10.0, "Intro"),
+                chapter(10.0, 10.0, "Marker"),
+                chapter(10.0, 25.0, "Main"),
+            ],
+            ..Default::default()
+        };
+
+        let paths = chapter_output_paths(
+            "/in/talk.mkv",
+            "/nonexistent-out",
+            "{title}",
+            "talk",
+            "mkv",
+            &info,
+            CollisionSettings::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            paths,
+            [
+                Some(PathBuf::from("/nonexistent-out/Intro.mkv")),
+                None,
+                Some(PathBuf::from("/nonexistent-out/Main.mkv")),
+            ]
+        );
+    }
+
+    #[test]
+    fn chapters_all_without_duration_are_rejected() {
+        let info = MediaInfo {
+            chapters: vec![chapter(5.0, 5.0, "Marker")],
+            ..Default::default()
+        };
+
+        let result = chapter_output_paths(
+            "/in/talk.mkv",
+            "/nonexistent-out",
+            "{title}",
+            "talk",
+            "mkv",
+            &info,
+            CollisionSettings::default(),
+        );
+        assert!(result.is_err());
+    }
+}
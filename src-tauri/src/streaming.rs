@@ -0,0 +1,368 @@
+//! HLS and DASH adaptive streaming packaging.
+//!
+//! Each rendition is encoded to its own keyframe-aligned intermediate MP4 so
+//! progress can be reported per rendition, then all renditions are packaged
+//! together with stream copy into the requested manifest format.
+
+use crate::error::FileDittoError;
+use crate::ffmpeg;
+use crate::job::{self, JobContext};
+use crate::metadata::{self, MediaInfo};
+use crate::output::{self, StagingDir};
+use crate::path;
+use crate::settings::{CollisionPolicy, CollisionSettings};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::AppHandle;
+use tracing::info;
+
+/// Default segment length in seconds.
+const DEFAULT_SEGMENT_DURATION: f64 = 6.0;
+
+/// File name of the HLS master playlist.
+const HLS_MASTER_PLAYLIST: &str = "master.m3u8";
+
+/// File name of the DASH manifest.
+const DASH_MANIFEST: &str = "manifest.mpd";
+
+/// Share of the overall progress bar given to encoding; packaging gets the rest.
+const ENCODE_PROGRESS_SHARE: f32 = 90.0;
+
+/// Adaptive streaming manifest formats.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamingFormat {
+    Hls,
+    Dash,
+}
+
+/// One quality level of the streaming ladder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rendition {
+    /// Frame height in pixels; the width follows the source aspect ratio.
+    pub height: u32,
+    pub video_bitrate_kbps: u32,
+    pub audio_bitrate_kbps: Option<u32>,
+}
+
+/// Options for packaging a video for adaptive streaming.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamingOptions {
+    pub format: StreamingFormat,
+    /// Renditions to encode; defaults to a 1080p/720p/480p ladder. Renditions
+    /// taller than the source are skipped.
+    #[serde(default)]
+    pub renditions: Vec<Rendition>,
+    /// Target segment length in seconds. Defaults to 6.
+    pub segment_duration: Option<f64>,
+    /// Seconds between forced keyframes; defaults to the segment duration so
+    /// every segment starts on a keyframe in every rendition.
+    pub keyframe_interval: Option<f64>,
+    /// Overrides the collision policy from settings, e.g. to retry after `output_exists`.
+    #[serde(default)]
+    pub collision_policy: Option<CollisionPolicy>,
+}
+
+/// Starts packaging `file_path` as an HLS or DASH stream into `output_dir`.
+///
+/// An existing package in `output_dir` is handled by the collision policy;
+/// with `auto_rename` the package goes to a numbered sibling folder. Returns
+/// the job ID; on completion the result's `output_path` is the master
+/// playlist (`master.m3u8`) or manifest (`manifest.mpd`).
+#[tauri::command]
+pub async fn package_streaming(
+    file_path: String,
+    output_dir: String,
+    options: StreamingOptions,
+    app_handle: AppHandle,
+) -> Result<String, FileDittoError> {
+    let (muxer, manifest) = match options.format {
+        StreamingFormat::Hls => ("hls", HLS_MASTER_PLAYLIST),
+        StreamingFormat::Dash => ("dash", DASH_MANIFEST),
+    };
+    ffmpeg::ensure_features_supported(&app_handle, &["libx264", muxer])?;
+
+    // Refuse or skip up front when the manifest exists; the complete package
+    // is checked again when it is moved into place.
+    let collision = CollisionSettings::load(&app_handle).with_policy(options.collision_policy);
+    output::resolve_collision(
+        &Path::new(&output_dir).join(manifest),
+        &[Path::new(&file_path)],
+        collision,
+        &job::reserved_outputs(&app_handle),
+    )?;
+
+    let job = JobContext::register(&app_handle, &file_path, Some(&output_dir));
+    let conversion_id = job.id.clone();
+
+    info!(
+        "Starting {:?} packaging: {} -> {} (Process ID: {})",
+        options.format,
+        file_path,
+        output_dir,
+        job.short_id()
+    );
+
+    job::spawn_job(job, move |job| async move {
+        perform_packaging(&file_path, &output_dir, &options, collision, &job).await
+    });
+
+    Ok(conversion_id)
+}
+
+async fn perform_packaging(
+    input_path: &str,
+    output_dir: &str,
+    options: &StreamingOptions,
+    collision: CollisionSettings,
+    job: &JobContext,
+) -> Result<String> {
+    let info = metadata::probe_media_info(input_path)?;
+    let renditions = select_renditions(&options.renditions, &info)?;
+    let segment_duration = options.segment_duration.unwrap_or(DEFAULT_SEGMENT_DURATION);
+    let keyframe_interval = options.keyframe_interval.unwrap_or(segment_duration);
+    if segment_duration <= 0.0 || keyframe_interval <= 0.0 {
+        return Err(FileDittoError::InvalidOptions(
+            "Segment duration and keyframe interval must be positive".into(),
+        )
+        .into());
+    }
+
+    // Intermediate renditions are scratch files; the package is staged
+    // separately and only moved into `output_dir` once it is complete.
+    let scratch = StagingDir::new(output_dir, &format!("{}_renditions", job.short_id()))?;
+    let package = StagingDir::new(output_dir, job.short_id())?;
+
+    let has_audio = info.has_audio();
+    let step = ENCODE_PROGRESS_SHARE / renditions.len() as f32;
+    let mut encoded = Vec::new();
+
+    for (index, rendition) in renditions.iter().enumerate() {
+        job.set_status(&format!(
+            "Encoding {}p ({}/{})",
+            rendition.height,
+            index + 1,
+            renditions.len()
+        ));
+
+        let output = scratch.path().join(format!("{}p.mp4", rendition.height));
+        let mut cmd = Command::new(path::ffmpeg_path());
+        cmd.args(["-y", "-i", input_path]);
+        cmd.args(["-map", "0:v:0"]);
+        if has_audio {
+            cmd.args(["-map", "0:a:0"]);
+        }
+        cmd.args(rendition_args(rendition, keyframe_interval, has_audio));
+        cmd.arg(&output);
+
+        let span_start = step * index as f32;
+        job.run_ffmpeg(&mut cmd, info.duration, (span_start, span_start + step))?;
+        encoded.push(output);
+    }
+
+    job.set_status("Packaging");
+    let (mut cmd, manifest) = match options.format {
+        StreamingFormat::Hls => hls_command(
+            &encoded,
+            &renditions,
+            package.path(),
+            segment_duration,
+            has_audio,
+        )?,
+        StreamingFormat::Dash => {
+            dash_command(&encoded, package.path(), segment_duration, has_audio)
+        }
+    };
+    job.run_ffmpeg(&mut cmd, info.duration, (ENCODE_PROGRESS_SHARE, 100.0))?;
+
+    let staged_manifest = package.path().join(manifest);
+    if !staged_manifest.exists() {
+        return Err(FileDittoError::FfmpegFailed(
+            format!("Manifest was not created: {}", manifest).into(),
+        )
+        .into());
+    }
+    let package_dir = package.commit_package(&[Path::new(input_path)], collision)?;
+
+    let manifest = package_dir.join(manifest);
+    info!("Streaming package written: {}", manifest.display());
+    Ok(manifest.to_string_lossy().to_string())
+}
+
+/// Encoder arguments for one keyframe-aligned rendition.
+fn rendition_args(rendition: &Rendition, keyframe_interval: f64, has_audio: bool) -> Vec<String> {
+    let bitrate = rendition.video_bitrate_kbps;
+    let mut args = vec![
+        "-vf".to_string(),
+        format!("scale=-2:{}", rendition.height),
+        "-c:v".to_string(),
+        "libx264".to_string(),
+        "-preset".to_string(),
+        "medium".to_string(),
+        "-pix_fmt".to_string(),
+        "yuv420p".to_string(),
+        "-b:v".to_string(),
+        format!("{}k", bitrate),
+        "-maxrate".to_string(),
+        format!("{}k", bitrate * 107 / 100),
+        "-bufsize".to_string(),
+        format!("{}k", bitrate * 3 / 2),
+        // Identical keyframe positions in every rendition let players switch
+        // quality at any segment boundary.
+        "-force_key_frames".to_string(),
+        format!("expr:gte(t,n_forced*{})", keyframe_interval),
+        "-sc_threshold".to_string(),
+        "0".to_string(),
+    ];
+
+    if has_audio {
+        args.extend([
+            "-c:a".to_string(),
+            "aac".to_string(),
+            "-ac".to_string(),
+            "2".to_string(),
+            "-b:a".to_string(),
+            format!("{}k", rendition.audio_bitrate_kbps.unwrap_or(128)),
+        ]);
+    }
+
+    args
+}
+
+/// Builds the HLS packaging command and returns it with the master playlist name.
+fn hls_command(
+    encoded: &[PathBuf],
+    renditions: &[Rendition],
+    output_dir: &Path,
+    segment_duration: f64,
+    has_audio: bool,
+) -> Result<(Command, &'static str)> {
+    let mut cmd = Command::new(path::ffmpeg_path());
+    cmd.arg("-y");
+    for input in encoded {
+        cmd.arg("-i").arg(input);
+    }
+
+    let mut stream_map = Vec::new();
+    for (index, rendition) in renditions.iter().enumerate() {
+        let name = format!("{}p", rendition.height);
+        fs::create_dir_all(output_dir.join(&name))
+            .map_err(|e| anyhow!("Failed to create rendition directory: {}", e))?;
+
+        cmd.args(["-map", &format!("{}:v:0", index)]);
+        if has_audio {
+            cmd.args(["-map", &format!("{}:a:0", index)]);
+            stream_map.push(format!("v:{i},a:{i},name:{name}", i = index, name = name));
+        } else {
+            stream_map.push(format!("v:{},name:{}", index, name));
+        }
+    }
+
+    cmd.args(["-c", "copy", "-f", "hls"]);
+    cmd.args(["-hls_time", &segment_duration.to_string()]);
+    cmd.args(["-hls_playlist_type", "vod"]);
+    cmd.args(["-var_stream_map", &stream_map.join(" ")]);
+    cmd.args(["-master_pl_name", HLS_MASTER_PLAYLIST]);
+    cmd.arg("-hls_segment_filename")
+        .arg(output_dir.join("%v").join("segment_%03d.ts"));
+    cmd.arg(output_dir.join("%v").join("index.m3u8"));
+
+    Ok((cmd, HLS_MASTER_PLAYLIST))
+}
+
+/// Builds the DASH packaging command and returns it with the manifest name.
+fn dash_command(
+    encoded: &[PathBuf],
+    output_dir: &Path,
+    segment_duration: f64,
+    has_audio: bool,
+) -> (Command, &'static str) {
+    let mut cmd = Command::new(path::ffmpeg_path());
+    cmd.arg("-y");
+    for input in encoded {
+        cmd.arg("-i").arg(input);
+    }
+
+    for index in 0..encoded.len() {
+        cmd.args(["-map", &format!("{}:v:0", index)]);
+    }
+    // Every rendition carries the same audio, so one copy is enough for DASH.
+    let adaptation_sets = if has_audio {
+        cmd.args(["-map", "0:a:0"]);
+        "id=0,streams=v id=1,streams=a"
+    } else {
+        "id=0,streams=v"
+    };
+
+    cmd.args(["-c", "copy", "-f", "dash"]);
+    cmd.args(["-seg_duration", &segment_duration.to_string()]);
+    cmd.args(["-use_template", "1", "-use_timeline", "1"]);
+    cmd.args(["-adaptation_sets", adaptation_sets]);
+    cmd.arg(output_dir.join(DASH_MANIFEST));
+
+    (cmd, DASH_MANIFEST)
+}
+
+/// Picks the renditions to encode, dropping any that would upscale the source.
+fn select_renditions(requested: &[Rendition], info: &MediaInfo) -> Result<Vec<Rendition>> {
+    let source_height = info.height.ok_or_else(|| {
+        FileDittoError::UnsupportedFormat("Input has no video stream to package".into())
+    })?;
+
+    let mut renditions: Vec<Rendition> = if requested.is_empty() {
+        default_ladder()
+    } else {
+        requested.to_vec()
+    };
+    if renditions
+        .iter()
+        .any(|r| r.height == 0 || r.video_bitrate_kbps == 0)
+    {
+        return Err(FileDittoError::InvalidOptions(
+            "Rendition height and bitrate must be positive".into(),
+        )
+        .into());
+    }
+
+    renditions.sort_by_key(|r| std::cmp::Reverse(r.height));
+    renditions.dedup_by_key(|r| r.height);
+
+    let fitting: Vec<Rendition> = renditions
+        .iter()
+        .filter(|r| r.height <= source_height)
+        .cloned()
+        .collect();
+
+    // A small source still gets one stream at the lowest requested quality.
+    if fitting.is_empty() {
+        let mut lowest = renditions.last().cloned().expect("renditions is not empty");
+        lowest.height = source_height / 2 * 2;
+        return Ok(vec![lowest]);
+    }
+
+    Ok(fitting)
+}
+
+/// The default 1080p/720p/480p ladder.
+fn default_ladder() -> Vec<Rendition> {
+    vec![
+        Rendition {
+            height: 1080,
+            video_bitrate_kbps: 5000,
+            audio_bitrate_kbps: Some(192),
+        },
+        Rendition {
+            height: 720,
+            video_bitrate_kbps: 2800,
+            audio_bitrate_kbps: Some(128),
+        },
+        Rendition {
+            height: 480,
+            video_bitrate_kbps: 1400,
+            audio_bitrate_kbps: Some(128),
+        },
+    ]
+}
@@ -0,0 +1,31 @@
+//! Fixtures shared by the unit tests.
+
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// A fresh directory under the system temp dir, removed on drop.
+pub struct TempDir(pub PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("fileditto-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        // Canonical so comparisons are not thrown off by a symlinked temp dir
+        Self(fs::canonicalize(dir).unwrap())
+    }
+
+    /// Writes `contents` to `relative`, creating parent folders.
+    pub fn file(&self, relative: &str, contents: &[u8]) -> PathBuf {
+        let path = self.0.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,367 @@
+//! Poster frame and contact sheet generation for video previews.
+
+use crate::error::FileDittoError;
+use crate::ffmpeg;
+use crate::job;
+use crate::metadata::{self, MediaInfo};
+use crate::output::{self, AtomicOutput};
+use crate::path;
+use crate::settings::{CollisionPolicy, CollisionSettings};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use tauri::AppHandle;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Image formats that preview images can be written as.
+const IMAGE_OUTPUT_FORMATS: &[&str] = &["jpg", "jpeg", "png", "webp", "bmp", "tiff"];
+
+/// Default width in pixels of a single contact sheet tile.
+const DEFAULT_TILE_WIDTH: u32 = 320;
+
+/// Frame rate assumed when the video stream does not report one.
+const FALLBACK_FRAME_RATE: f64 = 60.0;
+
+/// Options for extracting a single poster frame from a video.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PosterFrameOptions {
+    /// Position of the frame in seconds. Ignored when `representative` is set.
+    pub timestamp: Option<f64>,
+    /// Let FFmpeg's `thumbnail` filter pick the most representative frame.
+    pub representative: bool,
+    /// Output width in pixels; the height follows the source aspect ratio.
+    pub width: Option<u32>,
+    /// Overrides the collision policy from settings, e.g. to retry after `output_exists`.
+    #[serde(default)]
+    pub collision_policy: Option<CollisionPolicy>,
+}
+
+/// Options for building a contact sheet grid from a video.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContactSheetOptions {
+    pub columns: u32,
+    pub rows: u32,
+    /// Width in pixels of each tile; defaults to 320 or the source width if smaller.
+    pub tile_width: Option<u32>,
+    /// Burn each tile's timestamp into its bottom-left corner.
+    pub show_timestamps: bool,
+    /// Overrides the collision policy from settings, e.g. to retry after `output_exists`.
+    #[serde(default)]
+    pub collision_policy: Option<CollisionPolicy>,
+}
+
+/// Extracts a single frame from a video and saves it as an image.
+///
+/// Returns the path the image was written to, which the collision policy
+/// may have changed.
+#[tauri::command]
+pub async fn generate_poster_frame(
+    file_path: String,
+    output_path: String,
+    options: PosterFrameOptions,
+    app_handle: AppHandle,
+) -> Result<String, FileDittoError> {
+    let output_path = resolve_image_output(
+        &app_handle,
+        &file_path,
+        &output_path,
+        options.collision_policy,
+    )?;
+    info!("Generating poster frame: {} -> {}", file_path, output_path);
+
+    let info = probe_video(&file_path)?;
+    let output = new_image_output(&output_path)?;
+    let args = build_poster_frame_args(
+        &file_path,
+        &output.temp_path().to_string_lossy(),
+        &options,
+        &info,
+    )?;
+
+    run_image_command(&args, output).map_err(FileDittoError::from)
+}
+
+/// Builds a grid of evenly spaced frames from a video and saves it as a single image.
+///
+/// Returns the path the image was written to, which the collision policy
+/// may have changed.
+#[tauri::command]
+pub async fn generate_contact_sheet(
+    file_path: String,
+    output_path: String,
+    mut options: ContactSheetOptions,
+    app_handle: AppHandle,
+) -> Result<String, FileDittoError> {
+    let output_path = resolve_image_output(
+        &app_handle,
+        &file_path,
+        &output_path,
+        options.collision_policy,
+    )?;
+    info!(
+        "Generating {}x{} contact sheet: {} -> {}",
+        options.columns, options.rows, file_path, output_path
+    );
+
+    if options.show_timestamps && !timestamps_supported(&app_handle) {
+        warn!("FFmpeg build cannot draw text without a font file; leaving out timestamps");
+        options.show_timestamps = false;
+    }
+
+    let info = probe_video(&file_path)?;
+    let output = new_image_output(&output_path)?;
+    let args = build_contact_sheet_args(
+        &file_path,
+        &output.temp_path().to_string_lossy(),
+        &options,
+        &info,
+    )?;
+
+    run_image_command(&args, output).map_err(FileDittoError::from)
+}
+
+/// Applies the collision policy to a preview image path. Writing over the
+/// input video or another job's output is refused.
+fn resolve_image_output(
+    app_handle: &AppHandle,
+    file_path: &str,
+    output_path: &str,
+    collision_policy: Option<CollisionPolicy>,
+) -> Result<String, FileDittoError> {
+    let collision = CollisionSettings::load(app_handle).with_policy(collision_policy);
+    let resolved = output::resolve_collision(
+        Path::new(output_path),
+        &[Path::new(file_path)],
+        collision,
+        &job::reserved_outputs(app_handle),
+    )?;
+    if resolved != Path::new(output_path) {
+        info!("Output exists, writing to {} instead", resolved.display());
+    }
+    Ok(resolved.to_string_lossy().to_string())
+}
+
+/// Whether timestamps can be burned in. `drawtext` without a font file looks
+/// fonts up through fontconfig, which many Windows builds are compiled without.
+///
+/// Before the capability probe has finished, Windows builds are assumed to
+/// lack it.
+fn timestamps_supported(app_handle: &AppHandle) -> bool {
+    match ffmpeg::cached_capabilities(app_handle) {
+        Some(capabilities) => {
+            capabilities.has_filter("drawtext")
+                && capabilities
+                    .enabled_libraries
+                    .iter()
+                    .any(|library| library == "libfontconfig")
+        }
+        None => !cfg!(target_os = "windows"),
+    }
+}
+
+/// Probes a file and ensures it contains a video stream to take frames from.
+fn probe_video(file_path: &str) -> Result<MediaInfo> {
+    let info = metadata::probe_media_info(file_path)?;
+    if info.width.is_none() || info.height.is_none() {
+        return Err(FileDittoError::UnsupportedFormat(
+            format!("File has no video stream: {}", file_path).into(),
+        )
+        .into());
+    }
+    Ok(info)
+}
+
+/// Builds the FFmpeg arguments for a poster frame.
+fn build_poster_frame_args(
+    input_path: &str,
+    output_path: &str,
+    options: &PosterFrameOptions,
+    info: &MediaInfo,
+) -> Result<Vec<String>> {
+    let mut args = vec!["-y".to_string()];
+    let mut filters = Vec::new();
+
+    if options.representative {
+        filters.push("thumbnail".to_string());
+    } else {
+        let timestamp = options.timestamp.unwrap_or(0.0);
+        if timestamp < 0.0 {
+            return Err(FileDittoError::InvalidOptions(
+                format!("Timestamp cannot be negative: {}", timestamp).into(),
+            )
+            .into());
+        }
+        if let Some(duration) = info.duration {
+            if timestamp >= duration {
+                return Err(FileDittoError::InvalidOptions(
+                    format!(
+                        "Timestamp {:.2}s is beyond the video duration of {:.2}s",
+                        timestamp, duration
+                    )
+                    .into(),
+                )
+                .into());
+            }
+        }
+        args.extend(["-ss".to_string(), format!("{:.3}", timestamp)]);
+    }
+
+    if let Some(width) = options.width {
+        filters.push(format!("scale={}:-2", width));
+    }
+
+    args.extend(["-i".to_string(), input_path.to_string()]);
+    if !filters.is_empty() {
+        args.extend(["-vf".to_string(), filters.join(",")]);
+    }
+    args.extend(["-frames:v".to_string(), "1".to_string()]);
+    args.extend(image_quality_args(output_path)?);
+    args.push(output_path.to_string());
+
+    Ok(args)
+}
+
+/// Length of one frame of the first video stream, in seconds.
+fn frame_duration(info: &MediaInfo) -> f64 {
+    let frame_rate = info
+        .streams
+        .iter()
+        .find(|stream| stream.codec_type == "video")
+        .and_then(|stream| stream.frame_rate.as_deref())
+        .and_then(|rate| {
+            let (numerator, denominator) = rate.split_once('/').unwrap_or((rate, "1"));
+            let rate = numerator.parse::<f64>().ok()? / denominator.parse::<f64>().ok()?;
+            (rate.is_finite() && rate > 0.0).then_some(rate)
+        })
+        .unwrap_or(FALLBACK_FRAME_RATE);
+    1.0 / frame_rate
+}
+
+/// Builds the FFmpeg arguments for a contact sheet.
+fn build_contact_sheet_args(
+    input_path: &str,
+    output_path: &str,
+    options: &ContactSheetOptions,
+    info: &MediaInfo,
+) -> Result<Vec<String>> {
+    if options.columns == 0 || options.rows == 0 {
+        return Err(FileDittoError::InvalidOptions(
+            "Contact sheet needs at least one row and one column".into(),
+        )
+        .into());
+    }
+
+    let duration = info.duration.filter(|d| *d > 0.0).ok_or_else(|| {
+        FileDittoError::ProbeFailed(
+            format!("Could not determine video duration for {}", input_path).into(),
+        )
+    })?;
+
+    let tile_count = options.columns * options.rows;
+    let interval = duration / tile_count as f64;
+    if interval < frame_duration(info) {
+        return Err(FileDittoError::InvalidOptions(
+            format!(
+                "A {:.2}s video has fewer than the {} frames a {}x{} contact sheet needs",
+                duration, tile_count, options.columns, options.rows
+            )
+            .into(),
+        )
+        .into());
+    }
+
+    let tile_width = options
+        .tile_width
+        .unwrap_or(DEFAULT_TILE_WIDTH)
+        .min(info.width.unwrap_or(u32::MAX));
+
+    let mut filters = vec![
+        // Sample one frame per interval, starting half an interval in to skip black intro frames.
+        format!("fps=1/{:.6}:start_time={:.6}", interval, interval / 2.0),
+        format!("scale={}:-2", tile_width),
+    ];
+    if options.show_timestamps {
+        filters.push(
+            "drawtext=text='%{pts\\:hms}':x=5:y=h-th-5:fontsize=16:fontcolor=white:box=1:boxcolor=black@0.5"
+                .to_string(),
+        );
+    }
+    filters.push(format!(
+        "tile={}x{}:padding=4:margin=4",
+        options.columns, options.rows
+    ));
+
+    let mut args = vec![
+        "-y".to_string(),
+        "-i".to_string(),
+        input_path.to_string(),
+        "-vf".to_string(),
+        filters.join(","),
+        "-frames:v".to_string(),
+        "1".to_string(),
+    ];
+    args.extend(image_quality_args(output_path)?);
+    args.push(output_path.to_string());
+
+    Ok(args)
+}
+
+/// Validates the output image format and returns encoder arguments for it.
+fn image_quality_args(output_path: &str) -> Result<Vec<String>> {
+    let extension = Path::new(output_path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    if !IMAGE_OUTPUT_FORMATS.contains(&extension.as_str()) {
+        return Err(FileDittoError::UnsupportedFormat(
+            format!(
+                "Unsupported image format: '{}'. Supported formats: {}",
+                extension,
+                IMAGE_OUTPUT_FORMATS.join(", ")
+            )
+            .into(),
+        )
+        .into());
+    }
+
+    let args: &[&str] = match extension.as_str() {
+        "jpg" | "jpeg" => &["-q:v", "2"],
+        "webp" => &["-quality", "90"],
+        _ => &[],
+    };
+
+    Ok(args.iter().map(|a| a.to_string()).collect())
+}
+
+/// Runs a short FFmpeg image job and verifies the output was written.
+fn run_image_command(args: &[String], output: AtomicOutput) -> Result<String> {
+    let ffmpeg_path = path::ffmpeg_path();
+
+    let result = Command::new(&ffmpeg_path)
+        .args(args)
+        .output()
+        .map_err(|e| {
+            FileDittoError::FfmpegNotFound(format!("Failed to start FFmpeg process: {}", e).into())
+        })?;
+
+    if !result.status.success() {
+        let stderr_output = String::from_utf8_lossy(&result.stderr);
+        error!("FFmpeg image generation failed:\n{}", stderr_output);
+        return Err(FileDittoError::from_ffmpeg_stderr(&stderr_output).into());
+    }
+
+    let output_path = output.final_path().to_string_lossy().to_string();
+    output.commit()?;
+    info!("Preview image written: {}", output_path);
+    Ok(output_path)
+}
+
+/// Prepares an atomic output for a preview image. Previews run outside the
+/// job system, so the temporary name gets its own random tag.
+fn new_image_output(output_path: &str) -> Result<AtomicOutput> {
+    let tag = Uuid::new_v4().simple().to_string();
+    AtomicOutput::new(output_path, &tag[..8])
+}
@@ -4,8 +4,30 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
-// Re-export settings types for easier access
-pub use crate::settings::{OutputPathMode, OutputPathSettings, UserSettings};
+// Re-export settings and command option types for easier access
+pub use crate::animation::{AnimationFormat, AnimationOptions, DitherMode};
+pub use crate::concat::ConcatOptions;
+pub use crate::creation::{SlideshowOptions, TimelapseOptions};
+pub use crate::error::{ErrorDetails, FileDittoError};
+pub use crate::ffmpeg::{
+    CompatibilityWarning, FfmpegCapabilities, FfmpegVersion, VideoCodecOption,
+};
+pub use crate::filters::{
+    FrameRateOptions, OrientationOptions, OverlayContent, OverlayOptions, OverlayPosition,
+};
+pub use crate::path::{
+    BinaryCandidate, BinaryPairValidation, BinarySource, ResolvedBinaries, ResolvedBinary,
+};
+pub use crate::presets::{DevicePreset, PresetCheck};
+pub use crate::scan::{MediaKind, ScanOptions, ScannedFile};
+pub use crate::sequence::{FrameSelection, SequenceExportOptions, SequenceImportOptions};
+pub use crate::settings::{
+    BinaryPathSettings, CollisionPolicy, CollisionSettings, OutputPathMode, OutputPathSettings,
+    RenameStyle, UserSettings,
+};
+pub use crate::split::{SplitMode, SplitOptions};
+pub use crate::streaming::{Rendition, StreamingFormat, StreamingOptions};
+pub use crate::thumbnail::{ContactSheetOptions, PosterFrameOptions};
 
 /// Metadata information extracted from media files.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,6 +38,8 @@ pub struct FileMetadata {
     pub codec: Option<String>,
     pub format: Option<String>,
     pub size: Option<u64>,
+    /// Clockwise rotation players should apply, when the video carries a rotation tag.
+    pub rotation: Option<u32>,
 }
 
 /// Options for file conversion operations.
@@ -24,7 +48,31 @@ pub struct ConversionOptions {
     pub output_format: String,
     pub quality: String,
     pub output_dir: Option<String>,
+    /// Folder the batch was added from, used to mirror the folder structure.
+    #[serde(default)]
+    pub source_root: Option<String>,
     pub preserve_metadata: bool,
+    /// Optional watermark drawn on top of the video.
+    #[serde(default)]
+    pub overlay: Option<OverlayOptions>,
+    /// Optional rotation and flipping of the video.
+    #[serde(default)]
+    pub orientation: Option<OrientationOptions>,
+    /// Optional output frame rate change.
+    #[serde(default)]
+    pub frame_rate: Option<FrameRateOptions>,
+    /// Optional playback speed factor, e.g. 2.0 for double speed.
+    #[serde(default)]
+    pub speed: Option<f64>,
+    /// Optional device or platform preset ID. Overrides the format's codec and quality settings.
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// Optional video encoder, e.g. `libx265`. Defaults to the format's usual codec.
+    #[serde(default)]
+    pub video_codec: Option<String>,
+    /// Overrides the collision policy from settings, e.g. to retry after `output_exists`.
+    #[serde(default)]
+    pub collision_policy: Option<CollisionPolicy>,
 }
 
 /// Progress information for ongoing conversions.
@@ -45,7 +93,7 @@ pub struct ConversionResult {
     pub id: String,
     pub success: bool,
     pub output_path: Option<String>,
-    pub error: Option<String>,
+    pub error: Option<FileDittoError>,
 }
 
 /// Global state for tracking active conversions.
@@ -53,3 +101,6 @@ pub type ConversionState = Arc<Mutex<HashMap<String, ConversionProgress>>>;
 
 /// Global state for tracking active conversion process IDs for cancellation.
 pub type ProcessHandles = Arc<Mutex<HashMap<String, u32>>>;
+
+/// FFmpeg capability report, filled in by a probe at startup.
+pub type CapabilitiesCache = Arc<Mutex<Option<FfmpegCapabilities>>>;
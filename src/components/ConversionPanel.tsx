@@ -3,6 +3,7 @@ import { OutputSettings } from "./OutputSettings";
 import { FormatSettings } from "./FormatSettings";
 import { ConversionSummary } from "./ConversionSummary";
 import { FileItem } from "./FileListItem";
+import { OutputPathMode } from "../types/tauri";
 
 interface ConversionPanelProps {
   files: FileItem[];
@@ -10,10 +11,10 @@ interface ConversionPanelProps {
   setSelectedFormat: (format: string) => void;
   selectedQuality: string;
   setSelectedQuality: (quality: string) => void;
-  currentOutputMode: "same_as_input" | "custom_directory";
+  currentOutputMode: OutputPathMode;
   customDirectory: string;
   setCustomDirectory: (directory: string) => void;
-  onOutputModeChange: (mode: "same_as_input" | "custom_directory") => void;
+  onOutputModeChange: (mode: OutputPathMode) => void;
   onSelectOutputDirectory: () => void;
   onOpenOutputFolder?: (path?: string) => void;
   onStartConversion: () => void;
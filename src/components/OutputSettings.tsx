@@ -1,10 +1,11 @@
 import React from "react";
 import { Folder, FolderOpen } from "lucide-react";
+import { OutputPathMode } from "../types/tauri";
 
 interface OutputSettingsProps {
-  currentOutputMode: "same_as_input" | "custom_directory";
+  currentOutputMode: OutputPathMode;
   customDirectory: string;
-  onOutputModeChange: (mode: "same_as_input" | "custom_directory") => void;
+  onOutputModeChange: (mode: OutputPathMode) => void;
   onSelectOutputDirectory: () => void;
   onOpenOutputFolder?: (path?: string) => void;
   setCustomDirectory: (directory: string) => void;
@@ -18,6 +19,41 @@ export const OutputSettings: React.FC<OutputSettingsProps> = ({
   onOpenOutputFolder,
   setCustomDirectory,
 }) => {
+  // Folder picker shown under whichever directory-based mode is selected
+  const directoryPicker = (
+    <>
+      <div className="mt-2 flex gap-2">
+        <input
+          type="text"
+          value={customDirectory}
+          onChange={(e) => setCustomDirectory(e.target.value)}
+          placeholder="Choose output folder..."
+          className="flex-1 px-3 py-2 text-sm border border-gray-300 rounded-md shadow-sm focus:outline-none focus:ring-2 focus:ring-blue-500 focus:border-blue-500"
+        />
+        <button
+          onClick={onSelectOutputDirectory}
+          className="px-3 py-2 bg-gray-100 text-gray-700 rounded-md hover:bg-gray-200 transition-colors flex items-center"
+          title="Select output folder"
+        >
+          <Folder className="h-4 w-4" />
+        </button>
+        {customDirectory && onOpenOutputFolder && (
+          <button
+            onClick={() => onOpenOutputFolder(customDirectory)}
+            className="px-3 py-2 bg-blue-100 text-blue-700 rounded-md hover:bg-blue-200 transition-colors flex items-center"
+            title="Open output folder"
+          >
+            <FolderOpen className="h-4 w-4" />
+          </button>
+        )}
+      </div>
+
+      {customDirectory && (
+        <div className="mt-1 text-xs text-gray-500">{customDirectory}</div>
+      )}
+    </>
+  );
+
   return (
     <div className="bg-white rounded-lg shadow-sm border border-gray-200 p-6">
       <h3 className="text-lg font-medium text-gray-900 mb-4">
@@ -42,6 +78,21 @@ export const OutputSettings: React.FC<OutputSettingsProps> = ({
               </span>
             </label>
 
+            {/* Format Subfolder Option */}
+            <label className="flex items-center cursor-pointer">
+              <input
+                type="radio"
+                name="outputMode"
+                value="format_subfolder"
+                checked={currentOutputMode === "format_subfolder"}
+                onChange={() => onOutputModeChange("format_subfolder")}
+                className="h-4 w-4 text-blue-600 border-gray-300 focus:ring-blue-500"
+              />
+              <span className="ml-2 text-sm text-gray-700">
+                Subfolder per format next to the input file
+              </span>
+            </label>
+
             {/* Custom Directory Option */}
             <label className="flex items-start cursor-pointer">
               <input
@@ -55,40 +106,26 @@ export const OutputSettings: React.FC<OutputSettingsProps> = ({
               <div className="ml-2 flex-1">
                 <span className="text-sm text-gray-700">Custom directory</span>
 
-                {currentOutputMode === "custom_directory" && (
-                  <div className="mt-2 flex gap-2">
-                    <input
-                      type="text"
-                      value={customDirectory}
-                      onChange={(e) => setCustomDirectory(e.target.value)}
-                      placeholder="Choose output folder..."
-                      className="flex-1 px-3 py-2 text-sm border border-gray-300 rounded-md shadow-sm focus:outline-none focus:ring-2 focus:ring-blue-500 focus:border-blue-500"
-                    />
-                    <button
-                      onClick={onSelectOutputDirectory}
-                      className="px-3 py-2 bg-gray-100 text-gray-700 rounded-md hover:bg-gray-200 transition-colors flex items-center"
-                      title="Select output folder"
-                    >
-                      <Folder className="h-4 w-4" />
-                    </button>
-                    {customDirectory && onOpenOutputFolder && (
-                      <button
-                        onClick={() => onOpenOutputFolder(customDirectory)}
-                        className="px-3 py-2 bg-blue-100 text-blue-700 rounded-md hover:bg-blue-200 transition-colors flex items-center"
-                        title="Open output folder"
-                      >
-                        <FolderOpen className="h-4 w-4" />
-                      </button>
-                    )}
-                  </div>
-                )}
+                {currentOutputMode === "custom_directory" && directoryPicker}
+              </div>
+            </label>
+
+            {/* Mirror Folder Structure Option */}
+            <label className="flex items-start cursor-pointer">
+              <input
+                type="radio"
+                name="outputMode"
+                value="mirror_structure"
+                checked={currentOutputMode === "mirror_structure"}
+                onChange={() => onOutputModeChange("mirror_structure")}
+                className="h-4 w-4 text-blue-600 border-gray-300 focus:ring-blue-500 mt-0.5"
+              />
+              <div className="ml-2 flex-1">
+                <span className="text-sm text-gray-700">
+                  Custom directory, keeping source folder structure
+                </span>
 
-                {currentOutputMode === "custom_directory" &&
-                  customDirectory && (
-                    <div className="mt-1 text-xs text-gray-500">
-                      {customDirectory}
-                    </div>
-                  )}
+                {currentOutputMode === "mirror_structure" && directoryPicker}
               </div>
             </label>
           </div>
@@ -39,7 +39,7 @@ export const useConversion = ({
           updateFilesByConversionId(result.id, {
             status: result.success ? "completed" : "error",
             outputPath: result.output_path || undefined,
-            errorMessage: result.error || undefined,
+            errorMessage: result.error?.message,
           });
         }
       );
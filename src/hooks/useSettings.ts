@@ -1,5 +1,5 @@
 import { useState, useEffect } from 'react';
-import { UserSettings } from '../types/tauri';
+import { OutputPathMode, UserSettings } from '../types/tauri';
 import { TauriAPI } from '../utils/tauri';
 
 const defaultSettings: UserSettings = {
@@ -14,7 +14,7 @@ const defaultSettings: UserSettings = {
 
 export const useSettings = () => {
   const [userSettings, setUserSettings] = useState<UserSettings>(defaultSettings);
-  const [currentOutputMode, setCurrentOutputMode] = useState<"same_as_input" | "custom_directory">("same_as_input");
+  const [currentOutputMode, setCurrentOutputMode] = useState<OutputPathMode>("same_as_input");
   const [customDirectory, setCustomDirectory] = useState<string>("");
 
   useEffect(() => {
@@ -44,14 +44,15 @@ export const useSettings = () => {
     }
   };
 
-  const handleOutputModeChange = async (mode: "same_as_input" | "custom_directory") => {
+  const handleOutputModeChange = async (mode: OutputPathMode) => {
     setCurrentOutputMode(mode);
     
     const updatedSettings = {
       ...userSettings,
       output_path: {
+        ...userSettings.output_path,
         mode,
-        custom_directory: mode === "custom_directory" ? customDirectory || undefined : undefined,
+        custom_directory: TauriAPI.usesOutputDirectory(mode) ? customDirectory || undefined : undefined,
       },
     };
 
@@ -69,17 +70,20 @@ export const useSettings = () => {
       const directory = await TauriAPI.openDirectoryDialog();
       if (directory) {
         setCustomDirectory(directory);
-        
+        const directoryMode: OutputPathMode =
+          currentOutputMode === "mirror_structure" ? "mirror_structure" : "custom_directory";
+
         const updatedSettings = {
           ...userSettings,
           output_path: {
-            mode: "custom_directory" as const,
+            ...userSettings.output_path,
+            mode: directoryMode,
             custom_directory: directory,
           },
         };
 
         setUserSettings(updatedSettings);
-        setCurrentOutputMode("custom_directory");
+        setCurrentOutputMode(directoryMode);
 
         try {
           await TauriAPI.saveUserSettings(updatedSettings);
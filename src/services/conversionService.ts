@@ -1,4 +1,4 @@
-import { ConversionOptions } from '../types/tauri';
+import { ConversionOptions, OutputPathMode } from '../types/tauri';
 import { TauriAPI } from '../utils/tauri';
 import { FileItem } from '../components/FileListItem';
 
@@ -7,7 +7,7 @@ export class ConversionService {
     files: FileItem[],
     selectedFormat: string,
     selectedQuality: string,
-    currentOutputMode: "same_as_input" | "custom_directory",
+    currentOutputMode: OutputPathMode,
     customDirectory: string,
     preserveMetadata: boolean,
     updateFileStatus: (fileId: string, updates: Partial<FileItem>) => void
@@ -17,23 +17,24 @@ export class ConversionService {
     const options: ConversionOptions = {
       output_format: selectedFormat,
       quality: selectedQuality,
-      output_dir: currentOutputMode === "custom_directory" ? customDirectory || undefined : undefined,
+      output_dir: TauriAPI.usesOutputDirectory(currentOutputMode) ? customDirectory || undefined : undefined,
       preserve_metadata: preserveMetadata,
+      source_root:
+        currentOutputMode === "mirror_structure"
+          ? TauriAPI.commonDirectory(files.map((file) => file.path))
+          : undefined,
     };
 
     // Start conversion for each file (include pending, error, and completed files for retry)
-    for (const file of files) {
+    for (const [index, file] of files.entries()) {
       if (file.status === "converting") continue; // Skip files currently being converted
 
       try {
-        const outputPath =
-          currentOutputMode === "custom_directory" && customDirectory
-            ? TauriAPI.generateOutputPath(
-                file.path,
-                selectedFormat,
-                customDirectory
-              )
-            : TauriAPI.generateOutputPath(file.path, selectedFormat);
+        const outputPath = await TauriAPI.generateOutputPath(
+          file.path,
+          options,
+          index + 1
+        );
         const conversionId = await TauriAPI.convertFile(
           file.path,
           outputPath,
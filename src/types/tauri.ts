@@ -5,13 +5,61 @@ export interface FileMetadata {
   codec?: string;
   format?: string;
   size?: number;
+  rotation?: number;
 }
 
 export interface ConversionOptions {
   output_format: string;
   quality: string;
   output_dir?: string;
+  source_root?: string;
   preserve_metadata: boolean;
+  overlay?: OverlayOptions;
+  orientation?: OrientationOptions;
+  frame_rate?: FrameRateOptions;
+  speed?: number;
+  preset?: string;
+  video_codec?: string;
+  collision_policy?: CollisionPolicy;
+}
+
+export interface FrameRateOptions {
+  fps: number;
+  interpolate?: boolean;
+}
+
+export interface OrientationOptions {
+  auto_rotate?: boolean;
+  rotate?: 90 | 180 | 270;
+  flip_horizontal?: boolean;
+  flip_vertical?: boolean;
+}
+
+export type OverlayPosition =
+  | "top_left"
+  | "top_right"
+  | "bottom_left"
+  | "bottom_right"
+  | "center";
+
+export type OverlayContent =
+  | { kind: "image"; path: string }
+  | {
+      kind: "text";
+      text: string;
+      font?: string;
+      font_size?: number;
+      color?: string;
+    };
+
+export interface OverlayOptions {
+  content: OverlayContent;
+  position?: OverlayPosition;
+  offset_x?: number;
+  offset_y?: number;
+  opacity?: number;
+  start_time?: number;
+  end_time?: number;
 }
 
 export interface ConversionProgress {
@@ -27,7 +75,31 @@ export interface ConversionResult {
   id: string;
   success: boolean;
   output_path?: string;
-  error?: string;
+  error?: FileDittoError;
+}
+
+export type ErrorCode =
+  | "input_missing"
+  | "permission_denied"
+  | "unknown_encoder"
+  | "unsupported_format"
+  | "disk_full"
+  | "output_exists"
+  | "skipped"
+  | "cancelled"
+  | "ffmpeg_not_found"
+  | "ffmpeg_too_old"
+  | "probe_failed"
+  | "invalid_settings"
+  | "invalid_options"
+  | "ffmpeg_failed"
+  | "io"
+  | "internal";
+
+export interface FileDittoError {
+  code: ErrorCode;
+  message: string;
+  log_tail?: string;
 }
 
 export type ConversionStatus =
@@ -43,11 +115,56 @@ export interface UserSettings {
   preserve_metadata: boolean;
   compression_level: number;
   auto_delete: boolean;
+  binary_paths?: BinaryPathSettings;
+  log_retention_days?: number;
+  collision?: CollisionSettings;
+}
+
+export type CollisionPolicy = "overwrite" | "skip" | "auto_rename" | "ask";
+
+export type RenameStyle = "parentheses" | "underscore";
+
+export interface CollisionSettings {
+  policy: CollisionPolicy;
+  rename_style: RenameStyle;
+}
+
+export interface BinaryPathSettings {
+  ffmpeg_path?: string;
+  ffprobe_path?: string;
 }
 
 export interface OutputPathSettings {
   mode: OutputPathMode;
   custom_directory?: string;
+  filename_template?: string;
 }
 
-export type OutputPathMode = "same_as_input" | "custom_directory";
+export type OutputPathMode =
+  | "same_as_input"
+  | "custom_directory"
+  | "mirror_structure"
+  | "format_subfolder";
+
+export type MediaKind = "video" | "audio" | "image";
+
+export interface ScanOptions {
+  include?: string[];
+  exclude?: string[];
+  media_kinds?: MediaKind[];
+  min_size_bytes?: number;
+  max_size_bytes?: number;
+  min_duration?: number;
+  max_duration?: number;
+  follow_symlinks?: boolean;
+  include_hidden?: boolean;
+  include_metadata?: boolean;
+}
+
+export interface ScannedFile {
+  path: string;
+  root: string;
+  relative_path: string;
+  kind: MediaKind;
+  metadata?: FileMetadata;
+}
@@ -6,9 +6,21 @@ import {
   ConversionOptions,
   ConversionProgress,
   ConversionResult,
+  FileDittoError,
+  OutputPathMode,
+  ScannedFile,
+  ScanOptions,
   UserSettings,
 } from "../types/tauri";
 
+// Commands reject with a structured FileDittoError; fall back to plain strings
+const errorMessage = (error: unknown): string => {
+  if (error && typeof error === "object" && "message" in error) {
+    return (error as FileDittoError).message;
+  }
+  return String(error);
+};
+
 export class TauriAPI {
   // Check if FFmpeg is available
   static async checkFFmpegAvailability(): Promise<boolean> {
@@ -26,7 +38,7 @@ export class TauriAPI {
       return await invoke<FileMetadata>("extract_file_metadata", { filePath });
     } catch (error) {
       console.error("Error extracting metadata:", error);
-      throw new Error(`Failed to extract metadata: ${error}`);
+      throw new Error(`Failed to extract metadata: ${errorMessage(error)}`);
     }
   }
 
@@ -44,7 +56,7 @@ export class TauriAPI {
       });
     } catch (error) {
       console.error("Error starting conversion:", error);
-      throw new Error(`Failed to start conversion: ${error}`);
+      throw new Error(`Failed to start conversion: ${errorMessage(error)}`);
     }
   }
 
@@ -65,6 +77,19 @@ export class TauriAPI {
   //   }
   // }
 
+  // Expand folders into the media files they contain
+  static async scanFolders(
+    paths: string[],
+    options: ScanOptions = {}
+  ): Promise<ScannedFile[]> {
+    try {
+      return await invoke<ScannedFile[]>("scan_folders", { paths, options });
+    } catch (error) {
+      console.error("Error scanning folders:", error);
+      throw new Error(`Failed to scan folders: ${errorMessage(error)}`);
+    }
+  }
+
   // Cancel conversion
   static async cancelConversion(conversionId: string): Promise<boolean> {
     try {
@@ -160,25 +185,47 @@ export class TauriAPI {
     }
   }
 
-  // Generate output file path
-  static generateOutputPath(
+  // Build the output path for a file from the user's output settings
+  static async generateOutputPath(
     inputPath: string,
-    outputFormat: string,
-    outputDir?: string
-  ): string {
-    const pathParts = inputPath.replace(/\//g, "\\").split("\\");
-    const fileName = pathParts[pathParts.length - 1];
-    const baseName =
-      fileName.substring(0, fileName.lastIndexOf(".")) || fileName;
-    const newFileName = `${baseName}_converted.${outputFormat}`;
+    options: ConversionOptions,
+    index?: number
+  ): Promise<string> {
+    try {
+      return await invoke<string>("generate_output_path", {
+        inputPath,
+        options,
+        index,
+      });
+    } catch (error) {
+      console.error("Error generating output path:", error);
+      throw new Error(
+        `Failed to generate output path: ${errorMessage(error)}`
+      );
+    }
+  }
+
+  // Whether an output mode writes into the chosen output directory
+  static usesOutputDirectory(mode: OutputPathMode): boolean {
+    return mode === "custom_directory" || mode === "mirror_structure";
+  }
+
+  // Deepest folder containing every path, used as the root for mirrored output
+  static commonDirectory(paths: string[]): string | undefined {
+    if (paths.length === 0) return undefined;
 
-    if (outputDir) {
-      return `${outputDir}\\${newFileName}`;
-    } else {
-      // Use same directory as input file
-      pathParts[pathParts.length - 1] = newFileName;
-      return pathParts.join("\\");
+    const separator = paths[0].includes("\\") ? "\\" : "/";
+    const folders = paths.map((path) => path.split(/[\\/]/).slice(0, -1));
+    const common: string[] = [];
+    for (const [i, segment] of folders[0].entries()) {
+      if (!folders.every((parts) => parts[i] === segment)) break;
+      common.push(segment);
     }
+
+    // Paths on different drives share nothing
+    if (common.length === 0) return undefined;
+    // A bare root ("" on Unix, "C:" on Windows) needs its trailing separator
+    return common.length === 1 ? common[0] + separator : common.join(separator);
   }
 
   // Format file size
@@ -223,7 +270,7 @@ export class TauriAPI {
       return await invoke<UserSettings>("load_user_settings");
     } catch (error) {
       console.error("Error loading user settings:", error);
-      throw new Error(`Failed to load settings: ${error}`);
+      throw new Error(`Failed to load settings: ${errorMessage(error)}`);
     }
   }
 
@@ -233,7 +280,7 @@ export class TauriAPI {
       await invoke<void>("save_user_settings", { settings });
     } catch (error) {
       console.error("Error saving user settings:", error);
-      throw new Error(`Failed to save settings: ${error}`);
+      throw new Error(`Failed to save settings: ${errorMessage(error)}`);
     }
   }
 
@@ -243,36 +290,7 @@ export class TauriAPI {
       return await invoke<UserSettings>("reset_user_settings");
     } catch (error) {
       console.error("Error resetting user settings:", error);
-      throw new Error(`Failed to reset settings: ${error}`);
-    }
-  }
-
-  // Enhanced output path generation that uses settings
-  static generateOutputPathWithSettings(
-    inputPath: string,
-    outputFormat: string,
-    settings: UserSettings
-  ): string {
-    const pathParts = inputPath.replace(/\//g, "\\").split("\\");
-    const fileName = pathParts[pathParts.length - 1];
-    const baseName =
-      fileName.substring(0, fileName.lastIndexOf(".")) || fileName;
-    const newFileName = `${baseName}_converted.${outputFormat}`;
-
-    switch (settings.output_path.mode) {
-      case "custom_directory":
-        if (settings.output_path.custom_directory) {
-          return `${settings.output_path.custom_directory}\\${newFileName}`;
-        }
-        // Fallback to same as input if custom directory not set
-        pathParts[pathParts.length - 1] = newFileName;
-        return pathParts.join("\\");
-
-      case "same_as_input":
-      default:
-        // Use same directory as input file
-        pathParts[pathParts.length - 1] = newFileName;
-        return pathParts.join("\\");
+      throw new Error(`Failed to reset settings: ${errorMessage(error)}`);
     }
   }
 